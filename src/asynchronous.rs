@@ -0,0 +1,65 @@
+use std::{io, panic};
+
+use serde::{Deserialize, Serialize};
+use tokio::task;
+
+use crate::{Abseil, Outcome, Persist, Result};
+
+/// Async versions of [`Persist`]'s methods, for use within a Tokio runtime.
+///
+/// Each call runs its synchronous counterpart on Tokio's blocking thread pool, which is also
+/// how `tokio::fs` performs file IO. Locking, atomic writes, migrations and recovery therefore
+/// behave exactly as they do for the blocking API, and errors are the same.
+impl Persist {
+    pub async fn load_async<T>(&self) -> Result<Abseil<T>>
+    where
+        T: Default + for<'a> Deserialize<'a> + Send + 'static,
+    {
+        self.spawn_blocking(|persist| persist.load()).await
+    }
+
+    /// Load the default slot, reporting whether the [`Recovery`](crate::Recovery) policy had
+    /// to step in.
+    pub async fn load_with_outcome_async<T>(&self) -> Result<(Abseil<T>, Outcome)>
+    where
+        T: Default + for<'a> Deserialize<'a> + Send + 'static,
+    {
+        self.spawn_blocking(|persist| persist.load_with_outcome())
+            .await
+    }
+
+    pub async fn store_async(&self, state: impl Serialize + Send + 'static) -> Result<()> {
+        self.spawn_blocking(move |persist| persist.store(state))
+            .await
+    }
+
+    /// Modify the default slot in place while holding an exclusive lock on it.
+    ///
+    /// `f` runs on the blocking thread pool while the lock is held, so it shouldn't wait on
+    /// other async work.
+    pub async fn update_async<T, R>(
+        &self,
+        f: impl FnOnce(&mut T) -> R + Send + 'static,
+    ) -> Result<R>
+    where
+        T: Default + Serialize + for<'a> Deserialize<'a>,
+        R: Send + 'static,
+    {
+        self.spawn_blocking(move |persist| persist.update(f)).await
+    }
+
+    async fn spawn_blocking<R>(
+        &self,
+        f: impl FnOnce(&Persist) -> Result<R> + Send + 'static,
+    ) -> Result<R>
+    where
+        R: Send + 'static,
+    {
+        let persist = self.clone();
+        match task::spawn_blocking(move || f(&persist)).await {
+            Ok(result) => result,
+            Err(e) if e.is_panic() => panic::resume_unwind(e.into_panic()),
+            Err(e) => Err(io::Error::other(e).into()),
+        }
+    }
+}
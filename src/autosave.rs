@@ -0,0 +1,177 @@
+use std::{
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::{Persist, Result};
+
+/// How long state must go unchanged before it is written, unless configured otherwise.
+pub(crate) const DEFAULT_DELAY: Duration = Duration::from_secs(1);
+
+/// State which is written to a slot in the background whenever it changes.
+///
+/// Created by [`Persist::autosave`] or [`Slot::autosave`](crate::Slot::autosave). Changes made
+/// through [`Autosave::modify`] are written once the state has gone unchanged for the delay set
+/// with [`PersistBuilder::with_autosave_delay`](crate::PersistBuilder::with_autosave_delay), so
+/// a burst of changes results in a single store. Anything not yet written is stored when the
+/// handle is dropped.
+///
+/// A failed background write is retried after the next delay. Use [`Autosave::flush`] or
+/// [`Autosave::close`] to find out whether state has actually reached storage.
+pub struct Autosave<T: Clone + Serialize + Send + 'static> {
+    shared: Arc<Shared<T>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+struct Shared<T> {
+    persist: Persist,
+    slot: String,
+    inner: Mutex<Inner<T>>,
+    changed: Condvar,
+    /// Held while storing, so an older snapshot can never be written over a newer one.
+    writing: Mutex<()>,
+}
+
+struct Inner<T> {
+    state: T,
+    /// When the state was last changed, if it has changed since it was last stored.
+    changed_at: Option<Instant>,
+    closed: bool,
+}
+
+impl<T: Clone + Serialize + Send + 'static> Autosave<T> {
+    pub(crate) fn new(persist: &Persist, slot: String, state: T) -> Self {
+        let shared = Arc::new(Shared {
+            persist: persist.clone(),
+            slot,
+            inner: Mutex::new(Inner {
+                state,
+                changed_at: None,
+                closed: false,
+            }),
+            changed: Condvar::new(),
+            writing: Mutex::new(()),
+        });
+
+        let worker = thread::spawn({
+            let shared = shared.clone();
+            move || shared.run()
+        });
+
+        Self {
+            shared,
+            worker: Some(worker),
+        }
+    }
+
+    /// Read the current state.
+    pub fn read<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        f(&self.shared.inner().state)
+    }
+
+    /// Change the state, scheduling it to be written.
+    pub fn modify<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        let mut inner = self.shared.inner();
+        let result = f(&mut inner.state);
+        inner.changed_at = Some(Instant::now());
+        self.shared.changed.notify_one();
+        result
+    }
+
+    /// Write any unsaved changes now, without waiting for the delay.
+    pub fn flush(&self) -> Result<()> {
+        self.shared.save()
+    }
+
+    /// Stop saving in the background and write any unsaved changes.
+    ///
+    /// This is what happens when the handle is dropped, except that errors are reported.
+    pub fn close(mut self) -> Result<()> {
+        self.shutdown()
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        if let Some(worker) = self.worker.take() {
+            self.shared.inner().closed = true;
+            self.shared.changed.notify_one();
+            let _ = worker.join();
+        }
+
+        self.shared.save()
+    }
+}
+
+impl<T: Clone + Serialize + Send + 'static> Drop for Autosave<T> {
+    fn drop(&mut self) {
+        let _ = self.shutdown();
+    }
+}
+
+impl<T: Clone + Serialize + Send + 'static> std::fmt::Debug for Autosave<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Autosave")
+            .field("persist", &self.shared.persist)
+            .field("slot", &self.shared.slot)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Clone + Serialize> Shared<T> {
+    fn inner(&self) -> MutexGuard<'_, Inner<T>> {
+        // A panic in `modify` leaves whatever state it had reached, which is still worth saving.
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Wait for changes and store them once they have settled, until closed.
+    fn run(&self) {
+        let delay = self.persist.autosave_delay;
+        let mut inner = self.inner();
+
+        loop {
+            match inner.changed_at {
+                _ if inner.closed => return,
+                None => {
+                    inner = self
+                        .changed
+                        .wait(inner)
+                        .unwrap_or_else(PoisonError::into_inner);
+                }
+                Some(at) if at.elapsed() < delay => {
+                    inner = self
+                        .changed
+                        .wait_timeout(inner, delay.saturating_sub(at.elapsed()))
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .0;
+                }
+                Some(_) => {
+                    drop(inner);
+                    let _ = self.save();
+                    inner = self.inner();
+                }
+            }
+        }
+    }
+
+    /// Store the state if it has changed since it was last stored.
+    fn save(&self) -> Result<()> {
+        let _writing = self.writing.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let snapshot = {
+            let mut inner = self.inner();
+            if inner.changed_at.take().is_none() {
+                return Ok(());
+            }
+            inner.state.clone()
+        };
+
+        let result = self.persist.slot(&self.slot).store(snapshot);
+        if result.is_err() {
+            // Try again later, unless there have been changes since which will be tried anyway.
+            self.inner().changed_at.get_or_insert_with(Instant::now);
+        }
+        result
+    }
+}
@@ -0,0 +1,119 @@
+use std::{cmp::Reverse, time::Duration};
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::de::IgnoredAny;
+
+use crate::{storage::Storage, Abseil, Format, Result};
+
+/// How times are written into the names of backups and quarantined files, precise enough that
+/// names made in quick succession don't collide.
+pub(crate) const STAMP: &str = "%Y%m%dT%H%M%S%9fZ";
+
+/// How many previous generations of each slot to keep.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Backups {
+    /// Overwrite state without keeping a copy. This is the default.
+    #[default]
+    Disabled,
+    /// Keep the given number of most recent generations.
+    Keep(usize),
+    /// Keep every generation replaced within the given period.
+    MaxAge(Duration),
+}
+
+/// A previous generation of a slot's state.
+#[derive(Debug, Clone)]
+pub struct Backup {
+    /// Where the backup is kept; for the default filesystem storage, a path relative to the
+    /// storage directory.
+    pub key: String,
+    pub format: Format,
+    /// When this generation was replaced.
+    pub created: DateTime<Utc>,
+    /// The timestamp recorded in the backed up [`Abseil`], if it could be read.
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+/// Copy the current generation of a slot into `dir` and prune old generations.
+pub(crate) fn rotate(
+    storage: &dyn Storage,
+    policy: Backups,
+    dir: &str,
+    format: Format,
+    bytes: &[u8],
+) -> Result<()> {
+    if policy == Backups::Disabled {
+        return Ok(());
+    }
+
+    let now = Utc::now();
+    storage.write(
+        &format!("{dir}{}.{}", now.format(STAMP), format.extension()),
+        bytes,
+    )?;
+
+    // Pruning goes by the names alone, so that rotating doesn't have to read every backup.
+    let backups = generations(storage, dir)?;
+    let expired: Vec<_> = match policy {
+        Backups::Disabled => Vec::new(),
+        Backups::Keep(count) => backups.into_iter().skip(count).collect(),
+        Backups::MaxAge(age) => {
+            let cutoff = chrono::Duration::from_std(age)
+                .ok()
+                .and_then(|age| now.checked_sub_signed(age))
+                .unwrap_or(DateTime::<Utc>::MIN_UTC);
+            backups
+                .into_iter()
+                .filter(|backup| backup.created < cutoff)
+                .collect()
+        }
+    };
+
+    for backup in expired {
+        storage.delete(&backup.key)?;
+    }
+
+    Ok(())
+}
+
+/// List the backups in `dir`, newest first.
+pub(crate) fn list(storage: &dyn Storage, dir: &str) -> Result<Vec<Backup>> {
+    let mut backups = generations(storage, dir)?;
+    for backup in &mut backups {
+        backup.timestamp = storage
+            .read(&backup.key)
+            .ok()
+            .flatten()
+            .and_then(|bytes| backup.format.deserialize::<Abseil<IgnoredAny>>(&bytes).ok())
+            .map(|document| document.timestamp);
+    }
+    Ok(backups)
+}
+
+/// The backups in `dir`, newest first, as far as can be told from their keys.
+fn generations(storage: &dyn Storage, dir: &str) -> Result<Vec<Backup>> {
+    let mut backups = Vec::new();
+    for name in storage.list(dir)? {
+        let Some((stamp, extension)) = name.split_once('.') else {
+            continue;
+        };
+
+        let Some(format) = Format::from_extension(extension) else {
+            continue;
+        };
+
+        let Ok(created) = NaiveDateTime::parse_from_str(stamp, STAMP) else {
+            continue;
+        };
+
+        backups.push(Backup {
+            key: format!("{dir}{name}"),
+            format,
+            created: created.and_utc(),
+            timestamp: None,
+        });
+    }
+
+    backups.sort_by_key(|backup| Reverse(backup.created));
+    Ok(backups)
+}
@@ -0,0 +1,91 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::{
+    slot::{self, Slot},
+    storage::LockMode,
+    stringify, Abseil, Format, Persist, Result,
+};
+
+type Serializer<'a> = Box<dyn Fn(Format) -> stringify::Result<Vec<u8>> + 'a>;
+
+/// Several slots to be stored together.
+///
+/// Created by [`Persist::batch`]. Nothing is written until [`Batch::commit`], which holds the
+/// locks of every slot involved while writing. With storage that supports transactions, such
+/// as the SQLite backend, either every slot's new document is written or none are.
+///
+/// Only the documents themselves are covered by the transaction. Backups of the replaced state
+/// are taken before it and documents left under another format's extension are removed after
+/// it, so a failed commit may still leave new backups behind.
+pub struct Batch<'a> {
+    persist: &'a Persist,
+    documents: BTreeMap<String, Document<'a>>,
+}
+
+struct Document<'a> {
+    timestamp: DateTime<Utc>,
+    serialize: Serializer<'a>,
+}
+
+impl<'a> Batch<'a> {
+    pub(crate) fn new(persist: &'a Persist) -> Self {
+        Self {
+            persist,
+            documents: BTreeMap::new(),
+        }
+    }
+
+    /// Add `state` to be written to the slot named `slot`, replacing anything previously added
+    /// for the same slot.
+    pub fn store(&mut self, slot: impl Into<String>, state: impl Serialize + 'a) -> &mut Self {
+        let persist = self.persist;
+        let document = Abseil::new(state, persist.version);
+        self.documents.insert(
+            slot.into(),
+            Document {
+                timestamp: document.timestamp,
+                serialize: Box::new(move |format| persist.serialize(format, &document)),
+            },
+        );
+        self
+    }
+
+    /// Write every slot added to this batch.
+    pub fn commit(self) -> Result<()> {
+        let storage = self.persist.storage()?;
+
+        // Slots are visited in name order, so concurrent batches can't deadlock on each other.
+        let mut locks = Vec::with_capacity(self.documents.len());
+        let mut writes = Vec::with_capacity(self.documents.len());
+        for (name, document) in &self.documents {
+            let slot = Slot::new(self.persist, name);
+            locks.push(storage.lock(&slot.lock_key()?, LockMode::Exclusive)?);
+
+            let existing = slot.find(&*storage)?;
+            let format = slot.format_for(&existing);
+            let bytes = slot::preserve(&existing, format, (document.serialize)(format)?);
+            writes.push(slot.prepare(
+                &*storage,
+                existing,
+                format,
+                bytes,
+                document.timestamp,
+                self.persist.version,
+            )?);
+        }
+
+        slot::apply(self.persist, &*storage, &writes)
+    }
+}
+
+impl std::fmt::Debug for Batch<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Batch")
+            .field("persist", self.persist)
+            .field("slots", &self.documents.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
@@ -0,0 +1,61 @@
+use std::collections::BTreeMap;
+
+use serde_value::Value;
+
+use crate::{Error, Result};
+
+/// The value at a dotted `path` such as `window.width` within `value`, if there is one.
+///
+/// Each segment of a path names a key in a map, or an index into a sequence. Optional and
+/// newtype values are looked through, as they may or may not be marked in the stored format.
+pub(crate) fn get<'v>(mut value: &'v Value, path: &str) -> Option<&'v Value> {
+    for segment in path.split('.') {
+        value = match unwrap(value) {
+            Value::Map(map) => map.get(&Value::String(segment.to_owned()))?,
+            Value::Seq(seq) => seq.get(segment.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(unwrap(value))
+}
+
+/// Replace the value at `path` within `value`, creating maps along the way as needed.
+pub(crate) fn set(mut value: &mut Value, path: &str, field: Value) -> Result<()> {
+    let invalid = |reason: &str| Error::Value(format!("unable to set {path:?}: {reason}").into());
+
+    for segment in path.split('.') {
+        if segment.is_empty() {
+            return Err(invalid("empty path segment"));
+        }
+
+        value = match unwrap_mut(value) {
+            Value::Map(map) => map
+                .entry(Value::String(segment.to_owned()))
+                .or_insert_with(|| Value::Map(BTreeMap::new())),
+            Value::Seq(seq) => segment
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| seq.get_mut(index))
+                .ok_or_else(|| invalid("no such element"))?,
+            _ => return Err(invalid("not within a map or sequence")),
+        };
+    }
+
+    *value = field;
+    Ok(())
+}
+
+/// Look through the wrappers serde puts around optional and newtype values.
+fn unwrap(value: &Value) -> &Value {
+    match value {
+        Value::Option(Some(value)) | Value::Newtype(value) => unwrap(value),
+        value => value,
+    }
+}
+
+fn unwrap_mut(value: &mut Value) -> &mut Value {
+    match value {
+        Value::Option(Some(value)) | Value::Newtype(value) => unwrap_mut(value),
+        value => value,
+    }
+}
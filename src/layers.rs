@@ -0,0 +1,625 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    path::{Path, PathBuf},
+};
+
+use serde::{de::IgnoredAny, Deserialize, Serialize};
+use serde_value::Value;
+
+use crate::{
+    env_name,
+    slot::{Existing, Slot, DEFAULT_SLOT},
+    storage::{FileStorage, LockMode},
+    Abseil, Error, Result,
+};
+
+/// Separates nested keys in the names of environment variables, e.g. `MY_APP_WINDOW__WIDTH`.
+const ENV_SEPARATOR: &str = "__";
+
+/// Configuration assembled from several layers, each overriding the ones before it.
+///
+/// Created by [`Persist::layers`](crate::Persist::layers) or [`Slot::layers`]. From lowest to
+/// highest precedence, the layers are:
+///
+/// 1. Defaults, which are `T::default()` unless set with [`Layers::with_defaults`].
+/// 2. A system-wide file with the slot's name in the system directory: `/etc/<application>` on
+///    Unix, or `%ProgramData%\<application>` on Windows.
+/// 3. The slot itself, as [`Slot::load`] would read it.
+/// 4. Environment variables named after the application, as with
+///    [`Persist::env_var`](crate::Persist::env_var), and the slot unless it is the default one,
+///    followed by the path to a key: e.g. `MY_APP_WINDOW__WIDTH` sets `window.width`, and
+///    `MY_APP_RECENT_WINDOW__WIDTH` sets it in the slot named `recent`. Path segments are
+///    separated by a double underscore and match keys in lower layers regardless of case;
+///    segments naming keys found in no lower layer are lowercased.
+/// 5. Overrides, typically from the command line, set with [`Layers::with_override`].
+///
+/// Layers are deep-merged: maps are combined key by key, while anything else, including
+/// sequences, replaces what came before. Files may hold either a plain document or one written
+/// by [`Persist`](crate::Persist), which is migrated as usual. Strings from the environment or
+/// overrides are parsed as whatever type the key had in lower layers, so `MY_APP_WIDTH=800`
+/// can set a number. Where lower layers have no value for the key, or only `None`, strings
+/// which look like booleans or numbers are taken as such.
+pub struct Layers<'a, T> {
+    slot: Slot<'a>,
+    defaults: T,
+    system: Option<PathBuf>,
+    overrides: Vec<(String, Value)>,
+}
+
+/// Where a [`Layered`] configuration's value for a key came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    /// The system-wide file at this path.
+    System(PathBuf),
+    /// The slot, stored under this key.
+    User(String),
+    /// The environment variable with this name.
+    Environment(String),
+    Override,
+}
+
+/// Configuration loaded by [`Layers::load`], along with where each value came from.
+#[derive(Debug)]
+pub struct Layered<T> {
+    pub state: T,
+    sources: BTreeMap<String, Source>,
+}
+
+impl<'a, T> Layers<'a, T>
+where
+    T: Default + Serialize + for<'de> Deserialize<'de>,
+{
+    pub(crate) fn new(slot: Slot<'a>) -> Self {
+        Self {
+            slot,
+            defaults: T::default(),
+            system: None,
+            overrides: Vec::new(),
+        }
+    }
+
+    /// Use `defaults` as the lowest layer instead of `T::default()`.
+    pub fn with_defaults(self, defaults: T) -> Self {
+        Self { defaults, ..self }
+    }
+
+    /// Look for the system-wide file in `dir` instead of the platform's usual place.
+    pub fn with_system_dir(self, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            system: Some(dir.into()),
+            ..self
+        }
+    }
+
+    /// Set the value at a dotted `path`, such as `window.width`, over every other layer.
+    ///
+    /// Later overrides of the same path win.
+    pub fn with_override(mut self, path: impl Into<String>, value: impl Serialize) -> Result<Self> {
+        let value = serde_value::to_value(value).map_err(|e| Error::Value(e.into()))?;
+        self.overrides.push((path.into(), value));
+        Ok(self)
+    }
+
+    /// Read and merge every layer.
+    pub fn load(&self) -> Result<Layered<T>> {
+        let mut merged = Merged::default();
+        let defaults = serde_value::to_value(&self.defaults).map_err(|e| Error::Value(e.into()))?;
+        merged.apply(defaults, &Source::Default);
+
+        if let Some(dir) = self.system_dir() {
+            let storage = FileStorage::new(&dir);
+            if let Some(existing) = self.slot.find(&storage)? {
+                let state = self.state(&existing)?;
+                merged.apply(state, &Source::System(dir.join(&existing.key)));
+            }
+        }
+
+        let storage = self.slot.persist().storage()?;
+        let existing = {
+            let _lock = storage.lock(&self.slot.lock_key()?, LockMode::Shared)?;
+            self.slot.find(&*storage)?
+        };
+        if let Some(existing) = existing {
+            let state = self.state(&existing)?;
+            merged.apply(state, &Source::User(existing.key));
+        }
+
+        let prefix = self.env_prefix();
+        let own = self.slot.persist().env_var();
+        // Variables that aren't valid Unicode can't be meant for us, as our names are ASCII.
+        let mut vars: Vec<_> = env::vars_os()
+            .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+            .filter(|(name, _)| name.starts_with(&prefix) && *name != own)
+            .collect();
+        vars.sort();
+        for (name, value) in vars {
+            let path = name[prefix.len()..]
+                .split(ENV_SEPARATOR)
+                .map(str::to_owned)
+                .collect::<Vec<_>>();
+            merged.apply(
+                nest(&path, Value::String(value)),
+                &Source::Environment(name),
+            );
+        }
+
+        for (path, value) in &self.overrides {
+            let path: Vec<_> = path.split('.').map(str::to_owned).collect();
+            merged.apply(nest(&path, value.clone()), &Source::Override);
+        }
+
+        let state = merged
+            .value
+            .deserialize_into()
+            .map_err(|e| Error::Value(e.into()))?;
+
+        Ok(Layered {
+            state,
+            sources: merged.sources,
+        })
+    }
+
+    /// What the names of environment variables for this slot start with.
+    fn env_prefix(&self) -> String {
+        let mut prefix = self.slot.persist().env_prefix();
+        if self.slot.name() != DEFAULT_SLOT {
+            prefix.push('_');
+            prefix.push_str(&env_name(self.slot.name()));
+        }
+        prefix.push('_');
+        prefix
+    }
+
+    fn system_dir(&self) -> Option<PathBuf> {
+        if let Some(dir) = &self.system {
+            return Some(dir.clone());
+        }
+
+        let application = &self.slot.persist().application;
+        if cfg!(windows) {
+            env::var_os("ProgramData").map(|dir| Path::new(&dir).join(application))
+        } else {
+            Some(Path::new("/etc").join(application))
+        }
+    }
+
+    /// The state held in a file, which may or may not be wrapped in an [`Abseil`] envelope.
+    fn state(&self, existing: &Existing) -> Result<Value> {
+        let enveloped = existing
+            .format()
+            .deserialize::<Abseil<IgnoredAny>>(&existing.bytes)
+            .is_ok();
+
+        if enveloped {
+            let document: Abseil<Value> = self.slot.decode(existing)?;
+            Ok(document.state)
+        } else {
+            Ok(existing.format().deserialize(&existing.bytes)?)
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Layers<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Layers")
+            .field("slot", &self.slot)
+            .field("system", &self.system)
+            .field("overrides", &self.overrides)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> Layered<T> {
+    /// Which layer supplied the value at a dotted `path`, such as `window.width`.
+    ///
+    /// Values within sequences are attributed to the layer which supplied the whole sequence.
+    pub fn source(&self, path: &str) -> Option<&Source> {
+        let mut path = path;
+        loop {
+            if let Some(source) = self.sources.get(path) {
+                return Some(source);
+            }
+            path = path.rsplit_once('.')?.0;
+        }
+    }
+
+    /// Every value's dotted path along with the layer which supplied it.
+    pub fn sources(&self) -> impl Iterator<Item = (&str, &Source)> {
+        self.sources
+            .iter()
+            .map(|(path, source)| (path.as_str(), source))
+    }
+
+    pub fn into_inner(self) -> T {
+        self.state
+    }
+}
+
+/// Layers merged so far, along with where each leaf came from.
+struct Merged {
+    value: Value,
+    sources: BTreeMap<String, Source>,
+}
+
+impl Default for Merged {
+    fn default() -> Self {
+        Self {
+            value: Value::Map(BTreeMap::new()),
+            sources: BTreeMap::new(),
+        }
+    }
+}
+
+impl Merged {
+    fn apply(&mut self, layer: Value, source: &Source) {
+        merge(&mut self.value, layer, "", source, &mut self.sources);
+    }
+}
+
+fn merge(
+    base: &mut Value,
+    layer: Value,
+    path: &str,
+    source: &Source,
+    sources: &mut BTreeMap<String, Source>,
+) {
+    // Whether a value is marked as optional or newtype depends on where it came from, so
+    // either side may be wrapped when the other isn't.
+    match (base, layer) {
+        (base, Value::Option(Some(layer)) | Value::Newtype(layer)) => {
+            merge(base, *layer, path, source, sources)
+        }
+        (Value::Option(Some(base)) | Value::Newtype(base), layer)
+            if !matches!(layer, Value::Option(None) | Value::Unit) =>
+        {
+            merge(base, layer, path, source, sources)
+        }
+        (Value::Map(base), Value::Map(layer)) => {
+            for (key, value) in layer {
+                let key = match source {
+                    Source::Environment(_) => env_key(base, key),
+                    _ => key,
+                };
+                let path = join(path, &key);
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value, &path, source, sources),
+                    None => {
+                        let value = coerce(None, env_keys(value, source), source);
+                        attribute(&value, &path, source, sources);
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, layer) => {
+            let layer = coerce(Some(base), env_keys(layer, source), source);
+            let nested = format!("{path}.");
+            sources.retain(|key, _| !key.starts_with(&nested));
+            attribute(&layer, path, source, sources);
+            *base = layer;
+        }
+    }
+}
+
+/// The key in `base` which a path segment from an environment variable names.
+///
+/// Variable names are upper case by convention, so segments match keys regardless of case.
+/// Segments naming a new key are lowercased, which suits the usual field names.
+fn env_key(base: &BTreeMap<Value, Value>, key: Value) -> Value {
+    let Value::String(segment) = key else {
+        return key;
+    };
+
+    base.keys()
+        .find(|key| matches!(key, Value::String(key) if key.eq_ignore_ascii_case(&segment)))
+        .cloned()
+        .unwrap_or_else(|| Value::String(segment.to_lowercase()))
+}
+
+/// Lowercase the keys of maps nested in a layer from the environment, for when there's nothing
+/// below it to match them against.
+fn env_keys(layer: Value, source: &Source) -> Value {
+    match (layer, source) {
+        (Value::Map(map), Source::Environment(_)) => Value::Map(
+            map.into_iter()
+                .map(|(key, value)| (env_key(&BTreeMap::new(), key), env_keys(value, source)))
+                .collect(),
+        ),
+        (layer, _) => layer,
+    }
+}
+
+/// Record `source` as the origin of every leaf in `value`.
+fn attribute(value: &Value, path: &str, source: &Source, sources: &mut BTreeMap<String, Source>) {
+    match value {
+        Value::Map(map) if !map.is_empty() => {
+            for (key, value) in map {
+                attribute(value, &join(path, key), source, sources);
+            }
+        }
+        Value::Option(Some(value)) | Value::Newtype(value) => {
+            attribute(value, path, source, sources)
+        }
+        _ => {
+            sources.insert(path.to_owned(), source.clone());
+        }
+    }
+}
+
+/// Parse strings from the environment or an override as the type of the value they replace.
+///
+/// Where there's nothing to go by, because the key is new or its value is `None`, strings which
+/// look like a boolean or a number are taken as one. Anything that doesn't parse is kept as a
+/// string so that deserializing reports the mismatch.
+fn coerce(base: Option<&Value>, layer: Value, source: &Source) -> Value {
+    if !matches!(source, Source::Environment(_) | Source::Override) {
+        return layer;
+    }
+
+    let text = match layer {
+        Value::String(text) => text,
+        Value::Map(map) if base.is_none() => {
+            let map = map
+                .into_iter()
+                .map(|(key, value)| (key, coerce(None, value, source)))
+                .collect();
+            return Value::Map(map);
+        }
+        layer => return layer,
+    };
+
+    let parsed = match base {
+        Some(Value::Bool(_)) => text.parse().ok().map(Value::Bool),
+        Some(Value::U8(_) | Value::U16(_) | Value::U32(_) | Value::U64(_)) => {
+            text.parse().ok().map(Value::U64)
+        }
+        Some(Value::I8(_) | Value::I16(_) | Value::I32(_) | Value::I64(_)) => {
+            text.parse().ok().map(Value::I64)
+        }
+        Some(Value::F32(_) | Value::F64(_)) => text.parse().ok().map(Value::F64),
+        Some(Value::Char(_)) => text.parse().ok().map(Value::Char),
+        None | Some(Value::Option(None) | Value::Unit) => infer(&text),
+        Some(_) => None,
+    };
+
+    parsed.unwrap_or(Value::String(text))
+}
+
+/// The boolean or number `text` spells out, if any.
+fn infer(text: &str) -> Option<Value> {
+    if let Ok(value) = text.parse() {
+        return Some(Value::Bool(value));
+    }
+    if let Ok(value) = text.parse() {
+        return Some(Value::U64(value));
+    }
+    if let Ok(value) = text.parse() {
+        return Some(Value::I64(value));
+    }
+
+    // Infinities and NaN parse too, but are more likely meant as words.
+    let numeric = text.bytes().any(|byte| byte.is_ascii_digit());
+    text.parse().ok().filter(|_| numeric).map(Value::F64)
+}
+
+/// Wrap `value` in a map for each segment of `path`.
+fn nest(path: &[String], value: Value) -> Value {
+    path.iter().rev().fold(value, |value, key| {
+        Value::Map(BTreeMap::from([(Value::String(key.clone()), value)]))
+    })
+}
+
+fn join(path: &str, key: &Value) -> String {
+    let key = match key {
+        Value::String(key) => key.clone(),
+        Value::Char(key) => key.to_string(),
+        Value::Bool(key) => key.to_string(),
+        Value::U8(key) => key.to_string(),
+        Value::U16(key) => key.to_string(),
+        Value::U32(key) => key.to_string(),
+        Value::U64(key) => key.to_string(),
+        Value::I8(key) => key.to_string(),
+        Value::I16(key) => key.to_string(),
+        Value::I32(key) => key.to_string(),
+        Value::I64(key) => key.to_string(),
+        key => format!("{key:?}"),
+    };
+
+    if path.is_empty() {
+        key
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(text: &str) -> Value {
+        Value::String(text.to_owned())
+    }
+
+    fn map<const N: usize>(entries: [(&str, Value); N]) -> Value {
+        Value::Map(
+            entries
+                .into_iter()
+                .map(|(key, value)| (string(key), value))
+                .collect(),
+        )
+    }
+
+    fn env(name: &str) -> Source {
+        Source::Environment(name.to_owned())
+    }
+
+    #[test]
+    fn maps_are_merged_key_by_key() {
+        let mut merged = Merged::default();
+        merged.apply(
+            map([("width", Value::U32(800)), ("height", Value::U32(600))]),
+            &Source::Default,
+        );
+        merged.apply(map([("width", Value::U32(1024))]), &Source::Override);
+
+        assert_eq!(
+            merged.value,
+            map([("width", Value::U32(1024)), ("height", Value::U32(600))])
+        );
+        assert_eq!(merged.sources["width"], Source::Override);
+        assert_eq!(merged.sources["height"], Source::Default);
+    }
+
+    #[test]
+    fn optional_values_are_not_wrapped_twice() {
+        let mut merged = Merged::default();
+        merged.apply(
+            map([("width", Value::Option(Some(Box::new(Value::U32(800)))))]),
+            &Source::Default,
+        );
+        merged.apply(
+            map([("width", Value::Option(Some(Box::new(Value::U32(1024)))))]),
+            &Source::Override,
+        );
+
+        assert_eq!(
+            merged.value,
+            map([("width", Value::Option(Some(Box::new(Value::U32(1024)))))])
+        );
+    }
+
+    #[test]
+    fn optional_layers_merge_into_maps() {
+        let mut merged = Merged::default();
+        merged.apply(
+            map([("window", map([("width", Value::U32(800))]))]),
+            &Source::Default,
+        );
+        let window = map([("height", Value::U32(600))]);
+        merged.apply(
+            map([("window", Value::Option(Some(Box::new(window))))]),
+            &Source::Override,
+        );
+
+        assert_eq!(
+            merged.value,
+            map([(
+                "window",
+                map([("width", Value::U32(800)), ("height", Value::U32(600))])
+            )])
+        );
+    }
+
+    #[test]
+    fn none_replaces_a_value() {
+        let mut merged = Merged::default();
+        merged.apply(
+            map([("width", Value::Option(Some(Box::new(Value::U32(800)))))]),
+            &Source::Default,
+        );
+        merged.apply(map([("width", Value::Option(None))]), &Source::Override);
+
+        assert_eq!(merged.value, map([("width", Value::Option(None))]));
+    }
+
+    #[test]
+    fn strings_are_parsed_as_the_type_they_replace() {
+        let source = env("APP_WIDTH");
+        assert_eq!(
+            coerce(Some(&Value::U32(800)), string("1024"), &source),
+            Value::U64(1024)
+        );
+        assert_eq!(
+            coerce(Some(&Value::I8(-1)), string("-5"), &source),
+            Value::I64(-5)
+        );
+        assert_eq!(
+            coerce(Some(&Value::Bool(false)), string("true"), &source),
+            Value::Bool(true)
+        );
+        assert_eq!(
+            coerce(Some(&Value::F32(1.0)), string("2.5"), &source),
+            Value::F64(2.5)
+        );
+        assert_eq!(
+            coerce(Some(&string("name")), string("1024"), &source),
+            string("1024")
+        );
+        assert_eq!(
+            coerce(Some(&Value::U32(800)), string("wide"), &source),
+            string("wide")
+        );
+    }
+
+    #[test]
+    fn strings_without_a_type_are_inferred() {
+        let source = env("APP_WIDTH");
+        assert_eq!(
+            coerce(Some(&Value::Option(None)), string("1024"), &source),
+            Value::U64(1024)
+        );
+        assert_eq!(
+            coerce(Some(&Value::Unit), string("-3"), &source),
+            Value::I64(-3)
+        );
+        assert_eq!(coerce(None, string("0.5"), &source), Value::F64(0.5));
+        assert_eq!(coerce(None, string("false"), &source), Value::Bool(false));
+        assert_eq!(coerce(None, string("nan"), &source), string("nan"));
+        assert_eq!(coerce(None, string("wide"), &source), string("wide"));
+    }
+
+    #[test]
+    fn new_keys_from_the_environment_are_inferred() {
+        let mut merged = Merged::default();
+        merged.apply(map([]), &Source::Default);
+        merged.apply(
+            nest(&["window".into(), "width".into()], string("1024")),
+            &env("APP_WINDOW__WIDTH"),
+        );
+
+        assert_eq!(
+            merged.value,
+            map([("window", map([("width", Value::U64(1024))]))])
+        );
+        assert_eq!(merged.sources["window.width"], env("APP_WINDOW__WIDTH"));
+    }
+
+    #[test]
+    fn environment_keys_match_regardless_of_case() {
+        let mut merged = Merged::default();
+        merged.apply(
+            map([("Paths", map([("Home", string("/a"))]))]),
+            &Source::Default,
+        );
+        merged.apply(
+            nest(&["PATHS".into(), "HOME".into()], string("/b")),
+            &env("APP_PATHS__HOME"),
+        );
+        merged.apply(
+            nest(&["NEW".into(), "KEY".into()], string("x")),
+            &env("APP_NEW__KEY"),
+        );
+
+        assert_eq!(
+            merged.value,
+            map([
+                ("Paths", map([("Home", string("/b"))])),
+                ("new", map([("key", string("x"))])),
+            ])
+        );
+        assert_eq!(merged.sources["Paths.Home"], env("APP_PATHS__HOME"));
+        assert_eq!(merged.sources["new.key"], env("APP_NEW__KEY"));
+    }
+
+    #[test]
+    fn strings_from_files_are_left_alone() {
+        let source = Source::User("persist.json".to_owned());
+        assert_eq!(
+            coerce(Some(&Value::U32(800)), string("1024"), &source),
+            string("1024")
+        );
+        assert_eq!(coerce(None, string("1024"), &source), string("1024"));
+    }
+}
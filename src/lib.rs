@@ -1,16 +1,68 @@
-use std::{fmt, fs, io};
+use std::{
+    collections::BTreeMap,
+    env, fmt, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+#[cfg(feature = "sqlite")]
+use std::sync::{Mutex, PoisonError};
 
 use chrono::{DateTime, Utc};
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "tokio")]
+mod asynchronous;
+mod autosave;
+mod backup;
+mod batch;
+mod field;
+mod layers;
+mod loaded;
+mod location;
+mod migrate;
+mod recovery;
+mod slot;
+mod storage;
+mod stringify;
+#[cfg(feature = "watch")]
+mod watch;
+
+pub use autosave::Autosave;
+pub use backup::{Backup, Backups};
+pub use batch::Batch;
+pub use layers::{Layered, Layers, Source};
+pub use loaded::Loaded;
+pub use location::Location;
+pub use migrate::{BoxError, Migration};
+pub use recovery::{Outcome, Recovery};
+pub use serde_value::Value;
+pub use slot::Slot;
+#[cfg(feature = "sqlite")]
+pub use storage::SqliteStorage;
+pub use storage::{Entry, FileStorage, Lock, LockMode, MemoryStorage, Storage};
+pub use stringify::Format;
+#[cfg(feature = "watch")]
+pub use watch::Watch;
+
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
 #[derive(Debug)]
 pub enum Error {
-    AppData(Persist),
+    AppData(Box<Persist>),
     IO(io::Error),
+    /// Stored state was changed by someone else since it was loaded.
+    Conflict,
+    InvalidSlot(String),
+    /// Migrating state from the given schema version to the next one failed.
+    Migration(u32, BoxError),
     Serialization(stringify::Error),
+    /// State could not be converted to or from a [`Value`].
+    Value(BoxError),
+    /// State was written with a schema version newer than this build understands.
+    Version(u32),
 }
 
 impl From<Error> for io::Error {
@@ -39,7 +91,16 @@ impl fmt::Display for Error {
         match self {
             Error::AppData(persist) => write!(f, "unable to open storage for {persist}"),
             Error::IO(e) => e.fmt(f),
+            Error::Conflict => f.write_str("state was changed since it was loaded"),
+            Error::InvalidSlot(name) => write!(f, "invalid slot name {name:?}"),
+            Error::Migration(version, e) => {
+                write!(f, "unable to migrate state from version {version}: {e}")
+            }
             Error::Serialization(e) => e.fmt(f),
+            Error::Value(e) => write!(f, "unable to convert state: {e}"),
+            Error::Version(version) => {
+                write!(f, "state was written by a newer schema version ({version})")
+            }
         }
     }
 }
@@ -51,7 +112,17 @@ pub struct Persist {
     qualifier: Option<String>,
     organization: Option<String>,
     application: String,
+    base: Base,
+    location: Location,
+    format: Format,
+    convert: bool,
     pretty: bool,
+    version: u32,
+    migrations: BTreeMap<u32, Migration>,
+    recovery: Recovery,
+    backups: Backups,
+    autosave_delay: Duration,
+    storage: Backend,
 }
 
 impl Persist {
@@ -60,65 +131,234 @@ impl Persist {
             qualifier: None,
             organization: None,
             application: application.into(),
+            base: Base::ProjectDirs,
+            location: Location::default(),
+            format: Format::default(),
+            convert: false,
             pretty: true,
+            version: migrate::FIRST_VERSION,
+            migrations: BTreeMap::new(),
+            recovery: Recovery::default(),
+            backups: Backups::default(),
+            autosave_delay: autosave::DEFAULT_DELAY,
+            storage: Backend::Files,
         }
     }
 
     pub fn builder(application: impl Into<String>) -> PersistBuilder {
-        PersistBuilder(Persist {
-            qualifier: None,
-            organization: None,
-            application: application.into(),
-            pretty: true,
-        })
+        PersistBuilder(Persist::new(application))
     }
 
     pub fn load<T>(&self) -> Result<Abseil<T>>
     where
         T: Default + for<'a> Deserialize<'a>,
     {
-        let location = self.location()?;
-        let path = location.config_dir().join("persist.json");
+        self.slot(slot::DEFAULT_SLOT).load()
+    }
 
-        if !path.exists() {
-            return Ok(Abseil::new(Default::default()));
-        }
+    /// Load the default slot, reporting whether the [`Recovery`] policy had to step in.
+    pub fn load_with_outcome<T>(&self) -> Result<(Abseil<T>, Outcome)>
+    where
+        T: Default + for<'a> Deserialize<'a>,
+    {
+        self.slot(slot::DEFAULT_SLOT).load_with_outcome()
+    }
 
-        let text = fs::read_to_string(path)?;
-        Ok(stringify::from_str(&text)?)
+    /// Read the default slot without deserializing it, so that state can borrow from it.
+    pub fn load_borrowed(&self) -> Result<Loaded> {
+        self.slot(slot::DEFAULT_SLOT).load_borrowed()
     }
 
     pub fn store(&self, state: impl Serialize) -> Result<()> {
-        let location = self.location()?;
-        let dir = location.config_dir();
+        self.slot(slot::DEFAULT_SLOT).store(state)
+    }
 
-        if !dir.exists() {
-            fs::create_dir_all(dir)?;
-        }
+    /// Read a single field of the default slot's state by its dotted path.
+    pub fn get<V>(&self, path: &str) -> Result<Option<V>>
+    where
+        V: for<'a> Deserialize<'a>,
+    {
+        self.slot(slot::DEFAULT_SLOT).get(path)
+    }
+
+    /// Change a single field of the default slot's state by its dotted path.
+    pub fn set(&self, path: &str, value: impl Serialize) -> Result<()> {
+        self.slot(slot::DEFAULT_SLOT).set(path, value)
+    }
 
-        let path = dir.join("persist.json");
-        let text = self.stringify(state)?;
-        Ok(fs::write(path, text)?)
+    /// Store a previously loaded document, unless the default slot has changed since.
+    pub fn store_if_unchanged<T: Serialize>(&self, document: &mut Abseil<T>) -> Result<()> {
+        self.slot(slot::DEFAULT_SLOT).store_if_unchanged(document)
+    }
+
+    /// Modify the default slot in place while holding an exclusive lock on it.
+    pub fn update<T, R>(&self, f: impl FnOnce(&mut T) -> R) -> Result<R>
+    where
+        T: Default + Serialize + for<'a> Deserialize<'a>,
+    {
+        self.slot(slot::DEFAULT_SLOT).update(f)
+    }
+
+    /// List previous generations of the default slot, newest first.
+    pub fn backups(&self) -> Result<Vec<Backup>> {
+        self.slot(slot::DEFAULT_SLOT).backups()
+    }
+
+    /// Replace the default slot's state with that of a backup.
+    pub fn restore(&self, backup: &Backup) -> Result<()> {
+        self.slot(slot::DEFAULT_SLOT).restore(backup)
+    }
+
+    /// Hand `state` to a background thread which stores it in the default slot after changes.
+    pub fn autosave<T>(&self, state: T) -> Autosave<T>
+    where
+        T: Clone + Serialize + Send + 'static,
+    {
+        self.slot(slot::DEFAULT_SLOT).autosave(state)
     }
 
-    fn stringify(&self, state: impl Serialize) -> stringify::Result<String> {
-        if self.pretty {
-            stringify::to_string_pretty(&Abseil::new(state))
-        } else {
-            stringify::to_string(&Abseil::new(state))
+    /// Watch the default slot's file for changes made outside this process.
+    #[cfg(feature = "watch")]
+    pub fn watch<T>(&self) -> Result<Watch<T>>
+    where
+        T: for<'a> Deserialize<'a> + Send + 'static,
+    {
+        self.slot(slot::DEFAULT_SLOT).watch()
+    }
+
+    /// Assemble configuration from the default slot and the layers around it.
+    pub fn layers<T>(&self) -> Layers<'_, T>
+    where
+        T: Default + Serialize + for<'a> Deserialize<'a>,
+    {
+        self.slot(slot::DEFAULT_SLOT).layers()
+    }
+
+    /// Access a named document stored independently of the default one.
+    pub fn slot(&self, name: impl Into<String>) -> Slot<'_> {
+        Slot::new(self, name)
+    }
+
+    /// List the names of all slots currently on disk, including the default slot.
+    pub fn slots(&self) -> Result<Vec<String>> {
+        slot::list(self)
+    }
+
+    /// Start storing several slots together.
+    pub fn batch(&self) -> Batch<'_> {
+        Batch::new(self)
+    }
+
+    fn serialize(
+        &self,
+        format: Format,
+        document: &Abseil<impl Serialize>,
+    ) -> stringify::Result<Vec<u8>> {
+        format.serialize(document, self.pretty)
+    }
+
+    /// The environment variable which, when set, overrides where state is stored.
+    ///
+    /// This is the application name in upper case with anything other than letters and digits
+    /// replaced by underscores, followed by `_STATE_DIR`; e.g. `MY_APP_STATE_DIR`.
+    pub fn env_var(&self) -> String {
+        let mut name = self.env_prefix();
+        name.push_str("_STATE_DIR");
+        name
+    }
+
+    /// The application name as it appears in environment variables.
+    fn env_prefix(&self) -> String {
+        env_name(&self.application)
+    }
+
+    fn storage(&self) -> Result<Arc<dyn Storage>> {
+        match &self.storage {
+            Backend::Files => Ok(Arc::new(FileStorage::new(self.dir(self.location)?))),
+            #[cfg(feature = "sqlite")]
+            Backend::Sqlite(database) => {
+                let mut database = database.lock().unwrap_or_else(PoisonError::into_inner);
+                if let Some(database) = &*database {
+                    return Ok(database.clone());
+                }
+
+                let path = self.dir(Location::Data)?.join(SQLITE_FILE);
+                let opened = Arc::new(SqliteStorage::open(path)?);
+                *database = Some(opened.clone());
+                Ok(opened)
+            }
+            Backend::Custom(storage) => Ok(storage.clone()),
         }
     }
 
-    fn location(&self) -> Result<ProjectDirs> {
-        ProjectDirs::from(
+    /// The directory used for `location`.
+    ///
+    /// An explicit root wins, followed by the environment variable named by
+    /// [`Persist::env_var`], then portable mode, and finally the platform's project
+    /// directories.
+    fn dir(&self, location: Location) -> Result<PathBuf> {
+        if let Base::Root(root) = &self.base {
+            return Ok(root.join(location.dir_name()));
+        }
+
+        if let Some(root) = env::var_os(self.env_var()).filter(|root| !root.is_empty()) {
+            return Ok(PathBuf::from(root).join(location.dir_name()));
+        }
+
+        if let Base::Portable = self.base {
+            let exe = env::current_exe()?;
+            let root = exe.parent().unwrap_or_else(|| Path::new("."));
+            return Ok(root.join(location.dir_name()));
+        }
+
+        let dirs = ProjectDirs::from(
             self.qualifier.as_deref().unwrap_or(""),
             self.organization.as_deref().unwrap_or(""),
             &self.application,
         )
-        .ok_or_else(|| Error::AppData(self.clone()))
+        .ok_or_else(|| Error::AppData(Box::new(self.clone())))?;
+
+        Ok(location.resolve(&dirs))
     }
 }
 
+/// `name` in upper case with anything other than letters and digits replaced by underscores, as
+/// it appears in the names of environment variables.
+fn env_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Name of the database used by [`PersistBuilder::with_sqlite`], in the data directory.
+#[cfg(feature = "sqlite")]
+const SQLITE_FILE: &str = "abseil.sqlite3";
+
+/// Where a [`Persist`] keeps its slots.
+#[derive(Debug, Clone)]
+enum Backend {
+    /// Files in the directory for the configured [`Location`].
+    Files,
+    /// A database in the data directory, opened on first use and shared between clones.
+    #[cfg(feature = "sqlite")]
+    Sqlite(Arc<Mutex<Option<Arc<SqliteStorage>>>>),
+    Custom(Arc<dyn Storage>),
+}
+
+/// Where a [`Persist`] puts its directories.
+#[derive(Debug, Clone)]
+enum Base {
+    ProjectDirs,
+    Root(PathBuf),
+    Portable,
+}
+
 impl fmt::Display for Persist {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         if let Some(qualifier) = &self.qualifier {
@@ -139,7 +379,18 @@ impl fmt::Display for Persist {
 pub struct PersistBuilder(Persist);
 
 impl PersistBuilder {
+    /// Finish configuring the [`Persist`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if migrations were registered for [`Format::Ron`], which can't represent enums in
+    /// the [`Value`] migrations operate on.
     pub fn build(self) -> Persist {
+        #[cfg(feature = "ron")]
+        assert!(
+            self.0.format != Format::Ron || self.0.migrations.is_empty(),
+            "migrations can't be used with the RON format",
+        );
         self.0
     }
 
@@ -157,85 +408,144 @@ impl PersistBuilder {
         })
     }
 
-    /// Instruct [`Persist`] to use compact json format.
-    pub fn compact(self) -> Self {
+    /// Store state under `root` rather than the platform's project directories.
+    ///
+    /// Useful for tests and containers. Each [`Location`] is a subdirectory of `root`.
+    pub fn with_root(self, root: impl Into<PathBuf>) -> Self {
         Self(Persist {
-            pretty: false,
+            base: Base::Root(root.into()),
             ..self.0
         })
     }
-}
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct Abseil<T> {
-    pub timestamp: DateTime<Utc>,
-    pub state: T,
-}
-
-impl<T> Abseil<T> {
-    fn new(state: T) -> Self {
-        Self {
-            timestamp: Utc::now(),
-            state,
-        }
+    /// Store state next to the running executable, for installs that travel with their data.
+    ///
+    /// Each [`Location`] is a subdirectory of the executable's directory.
+    pub fn portable(self) -> Self {
+        Self(Persist {
+            base: Base::Portable,
+            ..self.0
+        })
     }
 
-    pub fn into_inner(self) -> T {
-        self.state
+    /// Keep state in `storage` rather than files in the application's directory.
+    ///
+    /// The directory settings are ignored. Pass a [`MemoryStorage`] to keep tests off the
+    /// filesystem.
+    pub fn with_storage(self, storage: impl Storage + 'static) -> Self {
+        Self(Persist {
+            storage: Backend::Custom(Arc::new(storage)),
+            ..self.0
+        })
     }
-}
-
-#[cfg(feature = "json")]
-mod stringify {
-    use serde::{Deserialize, Serialize};
 
-    pub type Result<T> = serde_json::Result<T>;
-
-    pub type Error = serde_json::Error;
+    /// Keep every slot in a single SQLite database in the application's data directory.
+    ///
+    /// This suits applications with many small documents, and lets a [`Batch`] write the
+    /// documents of several slots in one transaction. The configured [`Location`] is ignored; the directory is
+    /// otherwise chosen as for files. Use [`PersistBuilder::with_storage`] with a
+    /// [`SqliteStorage`] to put the database elsewhere.
+    #[cfg(feature = "sqlite")]
+    pub fn with_sqlite(self) -> Self {
+        Self(Persist {
+            storage: Backend::Sqlite(Arc::default()),
+            ..self.0
+        })
+    }
 
-    pub fn to_string(value: &impl Serialize) -> Result<String> {
-        serde_json::to_string(value)
+    /// Select which of the application's directories state is stored in.
+    pub fn with_location(self, location: Location) -> Self {
+        Self(Persist { location, ..self.0 })
     }
 
-    pub fn to_string_pretty(value: &impl Serialize) -> Result<String> {
-        serde_json::to_string_pretty(value)
+    /// Select the serialization format used for stored state.
+    pub fn with_format(self, format: Format) -> Self {
+        Self(Persist { format, ..self.0 })
     }
 
-    pub fn from_str<'a, T: Deserialize<'a>>(s: &'a str) -> Result<T> {
-        serde_json::from_str(s)
+    /// Rewrite state found in a different format using the configured one on the next store.
+    ///
+    /// By default, a file in another format (e.g. one hand-converted by a user) keeps that
+    /// format when it is written back.
+    pub fn convert_on_store(self) -> Self {
+        Self(Persist {
+            convert: true,
+            ..self.0
+        })
     }
-}
 
-#[cfg(all(feature = "toml", not(feature = "json")))]
-mod stringify {
-    use core::fmt;
+    /// Set the schema version written alongside stored state.
+    ///
+    /// State loaded with an older version is upgraded using the migrations registered with
+    /// [`PersistBuilder::with_migration`]. Documents written before versioning existed are
+    /// treated as version 1, which is also the default.
+    pub fn with_version(self, version: u32) -> Self {
+        Self(Persist { version, ..self.0 })
+    }
 
-    use either::Either;
-    use serde::{de::DeserializeOwned, Serialize};
+    /// Register the migration which upgrades state from version `from` to `from + 1`.
+    ///
+    /// Migrations can't be combined with [`Format::Ron`], and RON documents found under another
+    /// format's configuration fail to load if they are out of date.
+    pub fn with_migration(mut self, from: u32, migration: Migration) -> Self {
+        self.0.migrations.insert(from, migration);
+        self
+    }
 
-    pub type Result<T, E = Error> = std::result::Result<T, E>;
+    /// Choose what happens when stored state is corrupt or can't be migrated.
+    pub fn with_recovery(self, recovery: Recovery) -> Self {
+        Self(Persist { recovery, ..self.0 })
+    }
 
-    #[derive(Debug)]
-    pub struct Error(Either<toml::de::Error, toml::ser::Error>);
+    /// Keep previous generations of state when it is overwritten.
+    pub fn with_backups(self, backups: Backups) -> Self {
+        Self(Persist { backups, ..self.0 })
+    }
 
-    impl fmt::Display for Error {
-        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-            match &self.0 {
-                Either::Left(e) => e.fmt(f),
-                Either::Right(e) => e.fmt(f),
-            }
-        }
+    /// Set how long state must go unchanged before an [`Autosave`] writes it.
+    ///
+    /// The default is one second.
+    pub fn with_autosave_delay(self, autosave_delay: Duration) -> Self {
+        Self(Persist {
+            autosave_delay,
+            ..self.0
+        })
     }
 
-    pub fn to_string(value: &impl Serialize) -> Result<String> {
-        toml::to_string(value).map_err(|e| Error(Either::Right(e)))
+    /// Instruct [`Persist`] to use compact output.
+    ///
+    /// Binary formats are always compact, so this has no effect on them.
+    pub fn compact(self) -> Self {
+        Self(Persist {
+            pretty: false,
+            ..self.0
+        })
     }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Abseil<T> {
+    pub timestamp: DateTime<Utc>,
+    #[serde(default = "migrate::first_version")]
+    pub version: u32,
+    pub state: T,
+    /// Digest of the stored bytes this document was loaded from, used to detect changes made
+    /// by other processes.
+    #[serde(skip)]
+    digest: Option<u64>,
+}
 
-    pub fn to_string_pretty(value: &impl Serialize) -> Result<String> {
-        toml::to_string_pretty(value).map_err(|e| Error(Either::Right(e)))
+impl<T> Abseil<T> {
+    fn new(state: T, version: u32) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            version,
+            state,
+            digest: None,
+        }
     }
 
-    pub fn from_str<T: DeserializeOwned>(s: &str) -> Result<T> {
-        toml::from_str(s).map_err(|e| Error(Either::Left(e)))
+    pub fn into_inner(self) -> T {
+        self.state
     }
 }
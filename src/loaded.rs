@@ -0,0 +1,85 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_value::Value;
+
+use crate::{Abseil, Error, Format, Outcome, Result};
+
+/// A slot's stored document, read but not yet deserialized.
+///
+/// Created by [`Persist::load_borrowed`](crate::Persist::load_borrowed) or
+/// [`Slot::load_borrowed`](crate::Slot::load_borrowed). [`Loaded::get`] deserializes state which
+/// borrows from the buffer held here, so large read-mostly state needn't allocate a copy of
+/// every string.
+///
+/// Borrowed `&str` fields work with JSON, RON, YAML and MessagePack. TOML, CBOR and documents
+/// which had to be migrated always produce owned data; use `Cow<'_, str>` fields to accept both.
+#[derive(Debug)]
+pub struct Loaded {
+    contents: Contents,
+    version: u32,
+    digest: Option<u64>,
+    outcome: Outcome,
+}
+
+#[derive(Debug)]
+pub(crate) enum Contents {
+    /// There was nothing usable stored, so state is the default.
+    Missing,
+    /// A document at the current version, ready to be deserialized in place.
+    Stored { format: Format, bytes: Vec<u8> },
+    /// A document which was upgraded from an older version.
+    Migrated {
+        timestamp: DateTime<Utc>,
+        state: Value,
+    },
+}
+
+impl Loaded {
+    pub(crate) fn new(
+        contents: Contents,
+        version: u32,
+        digest: Option<u64>,
+        outcome: Outcome,
+    ) -> Self {
+        Self {
+            contents,
+            version,
+            digest,
+            outcome,
+        }
+    }
+
+    /// Whether the configured [`Recovery`](crate::Recovery) policy had to step in, as with
+    /// [`Persist::load_with_outcome`](crate::Persist::load_with_outcome).
+    pub fn outcome(&self) -> &Outcome {
+        &self.outcome
+    }
+
+    /// Deserialize the document, borrowing from this buffer where the format allows.
+    ///
+    /// Like any other loaded document, the result can be passed to
+    /// [`Persist::store_if_unchanged`](crate::Persist::store_if_unchanged).
+    pub fn get<'a, T>(&'a self) -> Result<Abseil<T>>
+    where
+        T: Default + Deserialize<'a>,
+    {
+        let document = match &self.contents {
+            Contents::Missing => Abseil::new(T::default(), self.version),
+            Contents::Stored { format, bytes } => format.deserialize_borrowed(bytes)?,
+            Contents::Migrated { timestamp, state } => Abseil {
+                timestamp: *timestamp,
+                version: self.version,
+                state: state
+                    .clone()
+                    .deserialize_into()
+                    .map_err(|e| Error::Value(e.into()))?,
+                digest: None,
+            },
+        };
+
+        Ok(Abseil {
+            digest: self.digest,
+            ..document
+        })
+    }
+}
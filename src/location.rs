@@ -0,0 +1,59 @@
+use std::path::PathBuf;
+
+use directories::ProjectDirs;
+
+/// Which of the application's standard directories state is stored in.
+///
+/// See [`ProjectDirs`] for where each of these lives on each platform. When state is stored
+/// under an explicit root (see [`PersistBuilder::with_root`]), each location is a subdirectory
+/// of that root instead.
+///
+/// [`PersistBuilder::with_root`]: crate::PersistBuilder::with_root
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Location {
+    /// Configuration, which may be roamed between machines. This is the default.
+    #[default]
+    Config,
+    /// Configuration specific to this machine.
+    ConfigLocal,
+    /// Application data, which may be roamed between machines.
+    Data,
+    /// Application data specific to this machine.
+    DataLocal,
+    /// Regenerable data which needn't be backed up.
+    Cache,
+    /// State which should persist between runs but isn't important enough to back up, such as
+    /// history or window layout. Platforms without a dedicated state directory use
+    /// [`Location::DataLocal`] instead.
+    State,
+    /// Preferences, as distinct from configuration on macOS.
+    Preference,
+}
+
+impl Location {
+    /// The subdirectory used for this location under an explicit root.
+    pub(crate) fn dir_name(self) -> &'static str {
+        match self {
+            Location::Config => "config",
+            Location::ConfigLocal => "config_local",
+            Location::Data => "data",
+            Location::DataLocal => "data_local",
+            Location::Cache => "cache",
+            Location::State => "state",
+            Location::Preference => "preference",
+        }
+    }
+
+    pub(crate) fn resolve(self, dirs: &ProjectDirs) -> PathBuf {
+        let dir = match self {
+            Location::Config => dirs.config_dir(),
+            Location::ConfigLocal => dirs.config_local_dir(),
+            Location::Data => dirs.data_dir(),
+            Location::DataLocal => dirs.data_local_dir(),
+            Location::Cache => dirs.cache_dir(),
+            Location::State => dirs.state_dir().unwrap_or_else(|| dirs.data_local_dir()),
+            Location::Preference => dirs.preference_dir(),
+        };
+        dir.to_path_buf()
+    }
+}
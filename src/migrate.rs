@@ -0,0 +1,55 @@
+use std::collections::BTreeMap;
+
+use serde_value::Value;
+
+use crate::{Error, Format, Result};
+
+/// The error type migrations may fail with.
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Upgrade a document's state from one schema version to the next.
+///
+/// Migrations operate on the raw state, before it has been deserialized into the application's
+/// type, so they can cope with fields that have since been renamed, retyped or removed.
+pub type Migration = fn(Value) -> std::result::Result<Value, BoxError>;
+
+/// The schema version assumed for documents written before versions were recorded.
+pub(crate) const FIRST_VERSION: u32 = 1;
+
+pub(crate) fn first_version() -> u32 {
+    FIRST_VERSION
+}
+
+/// Run `state`, read from a document in `format`, through every migration between `from` and
+/// `to`.
+#[cfg_attr(not(feature = "ron"), allow(unused_variables))]
+pub(crate) fn run(
+    migrations: &BTreeMap<u32, Migration>,
+    format: Format,
+    mut state: Value,
+    from: u32,
+    to: u32,
+) -> Result<Value> {
+    if from > to {
+        return Err(Error::Version(from));
+    }
+
+    // RON enums come out of a `Value` as whatever shape their contents had, so they would fail
+    // to deserialize even after a migration which changed nothing.
+    #[cfg(feature = "ron")]
+    if format == Format::Ron && from < to {
+        return Err(Error::Migration(
+            from,
+            "RON documents can't be migrated".into(),
+        ));
+    }
+
+    for version in from..to {
+        let migration = migrations
+            .get(&version)
+            .ok_or_else(|| Error::Migration(version, "no migration registered".into()))?;
+        state = migration(state).map_err(|e| Error::Migration(version, e))?;
+    }
+
+    Ok(state)
+}
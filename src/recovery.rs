@@ -0,0 +1,54 @@
+use chrono::Utc;
+
+use crate::{backup::STAMP, storage::Storage, Error, Result};
+
+/// What to do when stored state exists but can't be read.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Recovery {
+    /// Return the error to the caller. This is the default.
+    #[default]
+    Fail,
+    /// Ignore the stored state and start from `T::default()`. The bad file is overwritten by
+    /// the next store.
+    Default,
+    /// Move the bad file aside to `<file>.corrupt-<timestamp>` and start from `T::default()`.
+    Quarantine,
+}
+
+/// How a load completed, for callers that want to tell the user about recovered state.
+#[derive(Debug)]
+pub enum Outcome {
+    /// Stored state was read successfully.
+    Loaded,
+    /// There was no stored state, so the default was used.
+    Missing,
+    /// Stored state couldn't be read and was ignored in favor of the default.
+    Reset(Error),
+    /// Stored state couldn't be read and was moved to the given key (for the default
+    /// filesystem storage, a file name within the storage directory).
+    Quarantined(String, Error),
+}
+
+/// Whether `error` means the stored state itself is unusable, as opposed to a failure to reach
+/// it at all.
+pub(crate) fn is_corrupt(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::Migration(..) | Error::Serialization(_) | Error::Version(_)
+    )
+}
+
+/// Move the value under `key` out of the way, returning its new key.
+pub(crate) fn quarantine(storage: &dyn Storage, key: &str, bytes: &[u8]) -> Result<String> {
+    let stamp = Utc::now().format(STAMP).to_string();
+    let mut target = format!("{key}.corrupt-{stamp}");
+    // A clock too coarse to tell two quarantines apart mustn't cost the earlier file.
+    let mut attempt = 1;
+    while storage.read(&target)?.is_some() {
+        target = format!("{key}.corrupt-{stamp}-{attempt}");
+        attempt += 1;
+    }
+    storage.write(&target, bytes)?;
+    storage.delete(key)?;
+    Ok(target)
+}
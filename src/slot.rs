@@ -0,0 +1,769 @@
+use std::{
+    cell::OnceCell,
+    collections::BTreeMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    io,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{de::IgnoredAny, Deserialize, Serialize};
+use serde_value::Value;
+
+#[cfg(feature = "watch")]
+use crate::watch::Watch;
+use crate::{
+    autosave::Autosave,
+    backup::{self, Backup},
+    field,
+    layers::Layers,
+    loaded::{Contents, Loaded},
+    migrate,
+    recovery::{self, Outcome, Recovery},
+    storage::{Entry, LockMode, Storage},
+    Abseil, Error, Format, Persist, Result,
+};
+
+/// The slot used by [`Persist::load`] and [`Persist::store`].
+pub(crate) const DEFAULT_SLOT: &str = "persist";
+
+/// Directory, alongside the slots themselves, holding a subdirectory of backups per slot.
+const BACKUP_DIR: &str = "backups";
+
+/// Versions of this crate before formats were selectable named every file `*.json`, whatever
+/// its contents, so this extension is always looked for even without the `json` feature.
+const LEGACY_EXTENSION: &str = "json";
+
+/// Extension of the file each slot uses to coordinate access between processes.
+const LOCK_EXTENSION: &str = "lock";
+
+/// Device names Windows refuses to use as file names, regardless of extension.
+const RESERVED: &[&str] = &[
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8",
+    "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+/// A named document stored alongside the others belonging to a [`Persist`].
+///
+/// Each slot maps to its own file in the application's storage directory. Slot names may
+/// contain any characters; anything that isn't safe to use in a file name is escaped. That
+/// includes uppercase letters, so that slots whose names differ only in case get distinct files
+/// on case-insensitive filesystems such as those of Windows and macOS.
+#[derive(Debug, Clone)]
+pub struct Slot<'a> {
+    persist: &'a Persist,
+    name: String,
+}
+
+impl<'a> Slot<'a> {
+    pub(crate) fn new(persist: &'a Persist, name: impl Into<String>) -> Self {
+        Self {
+            persist,
+            name: name.into(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn persist(&self) -> &'a Persist {
+        self.persist
+    }
+
+    pub fn load<T>(&self) -> Result<Abseil<T>>
+    where
+        T: Default + for<'de> Deserialize<'de>,
+    {
+        self.load_with_outcome().map(|(document, _)| document)
+    }
+
+    /// Load this slot, reporting whether the configured [`Recovery`] policy had to step in.
+    pub fn load_with_outcome<T>(&self) -> Result<(Abseil<T>, Outcome)>
+    where
+        T: Default + for<'de> Deserialize<'de>,
+    {
+        let storage = self.persist.storage()?;
+        let lock_key = self.lock_key()?;
+        {
+            let _lock = storage.lock(&lock_key, LockMode::Shared)?;
+            if let Read::Done(loaded) = self.read(&*storage)? {
+                return Ok(loaded);
+            }
+        }
+
+        // Quarantining moves the file, which mustn't happen while others may be reading it. It
+        // may also have been fixed in the meantime, so it has to be read again.
+        let _lock = storage.lock(&lock_key, LockMode::Exclusive)?;
+        self.read(&*storage)?.or_quarantine(&*storage, |key, e| {
+            (self.fresh(), Outcome::Quarantined(key, e))
+        })
+    }
+
+    /// Read this slot, applying the configured [`Recovery`] policy short of quarantining, which
+    /// needs an exclusive lock.
+    fn read<T>(&self, storage: &dyn Storage) -> Result<Read<(Abseil<T>, Outcome)>>
+    where
+        T: Default + for<'de> Deserialize<'de>,
+    {
+        let Some(existing) = self.find(storage)? else {
+            return Ok(Read::Done((self.fresh(), Outcome::Missing)));
+        };
+
+        let e = match self.decode(&existing) {
+            Ok(document) => return Ok(Read::Done((document, Outcome::Loaded))),
+            Err(e) if !recovery::is_corrupt(&e) => return Err(e),
+            Err(e) => e,
+        };
+
+        match self.persist.recovery {
+            Recovery::Fail => Err(e),
+            Recovery::Default => {
+                // The bad file is still there, and it's fine for the reset state to replace it.
+                let document = Abseil {
+                    digest: Some(digest(&existing.bytes)),
+                    ..self.fresh()
+                };
+                Ok(Read::Done((document, Outcome::Reset(e))))
+            }
+            Recovery::Quarantine => Ok(Read::Corrupt(existing, e)),
+        }
+    }
+
+    fn fresh<T: Default>(&self) -> Abseil<T> {
+        Abseil::new(T::default(), self.persist.version)
+    }
+
+    pub(crate) fn decode<T>(&self, existing: &Existing) -> Result<Abseil<T>>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        // The common case of an up-to-date file in the expected format takes a single parse.
+        if let Some(document) = existing.parse_hinted::<Abseil<T>>() {
+            if document.version == self.persist.version {
+                return Ok(Abseil {
+                    digest: Some(digest(&existing.bytes)),
+                    ..document
+                });
+            }
+        }
+
+        let format = existing.format();
+        let document: Abseil<Value> = format.deserialize(&existing.bytes)?;
+        if document.version == self.persist.version {
+            let document: Abseil<T> = format.deserialize(&existing.bytes)?;
+            return Ok(Abseil {
+                digest: Some(digest(&existing.bytes)),
+                ..document
+            });
+        }
+
+        let version = document.version;
+        let state = migrate::run(
+            &self.persist.migrations,
+            format,
+            document.state,
+            version,
+            self.persist.version,
+        )?;
+        let state = state
+            .deserialize_into()
+            .map_err(|e| Error::Migration(version, e.into()))?;
+
+        Ok(Abseil {
+            timestamp: document.timestamp,
+            version: self.persist.version,
+            state,
+            digest: Some(digest(&existing.bytes)),
+        })
+    }
+
+    /// Read a single field of this slot's state by its dotted path, such as `window.width`.
+    ///
+    /// The state is migrated as usual but otherwise left raw, so the application's state type
+    /// isn't needed. Returns `None` if the slot or the field doesn't exist. Path segments name
+    /// keys in maps or indices into sequences.
+    pub fn get<V>(&self, path: &str) -> Result<Option<V>>
+    where
+        V: for<'de> Deserialize<'de>,
+    {
+        let storage = self.persist.storage()?;
+        let _lock = storage.lock(&self.lock_key()?, LockMode::Shared)?;
+        let Some(existing) = self.find(&*storage)? else {
+            return Ok(None);
+        };
+
+        let document: Abseil<Value> = self.decode(&existing)?;
+        field::get(&document.state, path)
+            .map(|value| value.clone().deserialize_into())
+            .transpose()
+            .map_err(|e| Error::Value(e.into()))
+    }
+
+    /// Change a single field of this slot's state by its dotted path, such as `window.width`.
+    ///
+    /// Maps are created along the path as needed, including the state itself if the slot
+    /// doesn't exist yet. Everything else in the document is written back as it was read.
+    ///
+    /// This isn't supported for documents stored as RON, which distinguishes structs from maps.
+    pub fn set(&self, path: &str, value: impl Serialize) -> Result<()> {
+        let value = serde_value::to_value(value).map_err(|e| Error::Value(e.into()))?;
+
+        let storage = self.persist.storage()?;
+        let _lock = storage.lock(&self.lock_key()?, LockMode::Exclusive)?;
+        let existing = self.find(&*storage)?;
+
+        // Structs and maps look alike once read as a `Value`, but RON writes them differently.
+        #[cfg(feature = "ron")]
+        if self.format_for(&existing) == Format::Ron {
+            let e = "setting individual fields of RON documents is not supported";
+            return Err(Error::Value(e.into()));
+        }
+
+        let mut state = match &existing {
+            Some(existing) => self.decode::<Value>(existing)?.state,
+            None => Value::Map(BTreeMap::new()),
+        };
+
+        field::set(&mut state, path, value)?;
+        let document = Abseil::new(state, self.persist.version);
+        self.store_locked(&*storage, existing, &document)?;
+        Ok(())
+    }
+
+    /// Read this slot without deserializing it, so that state can borrow from the stored bytes.
+    ///
+    /// The configured [`Recovery`] policy applies if the document can't be parsed at all, or
+    /// can't be migrated; [`Loaded::outcome`] tells whether it did. Errors in the state itself
+    /// are reported by [`Loaded::get`].
+    pub fn load_borrowed(&self) -> Result<Loaded> {
+        let storage = self.persist.storage()?;
+        let lock_key = self.lock_key()?;
+        {
+            let _lock = storage.lock(&lock_key, LockMode::Shared)?;
+            if let Read::Done(loaded) = self.read_borrowed(&*storage)? {
+                return Ok(loaded);
+            }
+        }
+
+        // As with `load_with_outcome`, quarantining takes an exclusive lock and a second look.
+        let version = self.persist.version;
+        let _lock = storage.lock(&lock_key, LockMode::Exclusive)?;
+        self.read_borrowed(&*storage)?
+            .or_quarantine(&*storage, |key, e| {
+                Loaded::new(
+                    Contents::Missing,
+                    version,
+                    None,
+                    Outcome::Quarantined(key, e),
+                )
+            })
+    }
+
+    fn read_borrowed(&self, storage: &dyn Storage) -> Result<Read<Loaded>> {
+        let version = self.persist.version;
+        let Some(existing) = self.find(storage)? else {
+            let loaded = Loaded::new(Contents::Missing, version, None, Outcome::Missing);
+            return Ok(Read::Done(loaded));
+        };
+
+        let digest = digest(&existing.bytes);
+        let e = match self.contents(&existing) {
+            Ok(Some(contents)) => {
+                let loaded = Loaded::new(contents, version, Some(digest), Outcome::Loaded);
+                return Ok(Read::Done(loaded));
+            }
+            Ok(None) => {
+                let contents = Contents::Stored {
+                    format: existing.format(),
+                    bytes: existing.bytes,
+                };
+                let loaded = Loaded::new(contents, version, Some(digest), Outcome::Loaded);
+                return Ok(Read::Done(loaded));
+            }
+            Err(e) if !recovery::is_corrupt(&e) => return Err(e),
+            Err(e) => e,
+        };
+
+        match self.persist.recovery {
+            Recovery::Fail => Err(e),
+            Recovery::Default => Ok(Read::Done(Loaded::new(
+                Contents::Missing,
+                version,
+                Some(digest),
+                Outcome::Reset(e),
+            ))),
+            Recovery::Quarantine => Ok(Read::Corrupt(existing, e)),
+        }
+    }
+
+    /// Check that `existing` is a usable document, migrating it if it is out of date.
+    ///
+    /// Returns `None` if it is already at the current version.
+    fn contents(&self, existing: &Existing) -> Result<Option<Contents>> {
+        if let Some(header) = existing.parse_hinted::<Abseil<IgnoredAny>>() {
+            if header.version == self.persist.version {
+                return Ok(None);
+            }
+        }
+
+        let format = existing.format();
+        let document: Abseil<Value> = format.deserialize(&existing.bytes)?;
+        if document.version == self.persist.version {
+            return Ok(None);
+        }
+
+        let version = document.version;
+        let state = migrate::run(
+            &self.persist.migrations,
+            format,
+            document.state,
+            version,
+            self.persist.version,
+        )?;
+
+        Ok(Some(Contents::Migrated {
+            timestamp: document.timestamp,
+            state,
+        }))
+    }
+
+    /// Write `state` to this slot.
+    ///
+    /// If the slot currently exists in a format other than the configured one, it is rewritten
+    /// in the format it was found in, unless [`PersistBuilder::convert_on_store`] was used.
+    /// Rewriting a TOML file keeps its comments, whitespace and key order, updating values in
+    /// place.
+    ///
+    /// [`PersistBuilder::convert_on_store`]: crate::PersistBuilder::convert_on_store
+    pub fn store(&self, state: impl Serialize) -> Result<()> {
+        let storage = self.persist.storage()?;
+        let _lock = storage.lock(&self.lock_key()?, LockMode::Exclusive)?;
+        let document = Abseil::new(state, self.persist.version);
+        self.store_locked(&*storage, self.find(&*storage)?, &document)?;
+        Ok(())
+    }
+
+    /// Store a document previously loaded from this slot, unless the slot has changed since.
+    ///
+    /// If the stored state differs from what `document` was loaded from, or if a document
+    /// which wasn't loaded from this slot is older than what's stored, nothing is written and
+    /// [`Error::Conflict`] is returned. On success, `document` is updated so that it can be
+    /// modified and stored again.
+    pub fn store_if_unchanged<T: Serialize>(&self, document: &mut Abseil<T>) -> Result<()> {
+        let storage = self.persist.storage()?;
+        let _lock = storage.lock(&self.lock_key()?, LockMode::Exclusive)?;
+        let existing = self.find(&*storage)?;
+
+        let unchanged = match (&existing, document.digest) {
+            (None, None) => true,
+            (None, Some(_)) => false,
+            (Some(existing), Some(loaded)) => digest(&existing.bytes) == loaded,
+            (Some(existing), None) => existing
+                .format()
+                .deserialize::<Abseil<IgnoredAny>>(&existing.bytes)
+                .is_ok_and(|stored| stored.timestamp <= document.timestamp),
+        };
+
+        if !unchanged {
+            return Err(Error::Conflict);
+        }
+
+        document.timestamp = Utc::now();
+        document.version = self.persist.version;
+        document.digest = Some(self.store_locked(&*storage, existing, document)?);
+        Ok(())
+    }
+
+    /// Load this slot, modify it and store the result, holding an exclusive lock throughout so
+    /// that no other process can change the state in the meantime.
+    pub fn update<T, R>(&self, f: impl FnOnce(&mut T) -> R) -> Result<R>
+    where
+        T: Default + Serialize + for<'de> Deserialize<'de>,
+    {
+        let storage = self.persist.storage()?;
+        let _lock = storage.lock(&self.lock_key()?, LockMode::Exclusive)?;
+        let (document, _) = self
+            .read::<T>(&*storage)?
+            .or_quarantine(&*storage, |key, e| {
+                (self.fresh(), Outcome::Quarantined(key, e))
+            })?;
+        let mut document = Abseil::new(document.state, self.persist.version);
+        let result = f(&mut document.state);
+        self.store_locked(&*storage, self.find(&*storage)?, &document)?;
+        Ok(result)
+    }
+
+    /// Hand `state` to a background thread which stores it in this slot after changes.
+    pub fn autosave<T>(&self, state: T) -> Autosave<T>
+    where
+        T: Clone + Serialize + Send + 'static,
+    {
+        Autosave::new(self.persist, self.name.clone(), state)
+    }
+
+    /// Watch this slot's file for changes made outside this process.
+    #[cfg(feature = "watch")]
+    pub fn watch<T>(&self) -> Result<Watch<T>>
+    where
+        T: for<'de> Deserialize<'de> + Send + 'static,
+    {
+        Watch::new(self.persist, self)
+    }
+
+    /// Assemble configuration from this slot and the layers around it.
+    pub fn layers<T>(&self) -> Layers<'a, T>
+    where
+        T: Default + Serialize + for<'de> Deserialize<'de>,
+    {
+        Layers::new(self.clone())
+    }
+
+    /// Write `document` over `existing`, returning the digest of what was written.
+    fn store_locked(
+        &self,
+        storage: &dyn Storage,
+        existing: Option<Existing>,
+        document: &Abseil<impl Serialize>,
+    ) -> Result<u64> {
+        let format = self.format_for(&existing);
+        let bytes = preserve(&existing, format, self.persist.serialize(format, document)?);
+        let write = self.prepare(
+            storage,
+            existing,
+            format,
+            bytes,
+            document.timestamp,
+            document.version,
+        )?;
+        let digest = digest(&write.bytes);
+        apply(self.persist, storage, &[write])?;
+        Ok(digest)
+    }
+
+    /// The format to write this slot in, given what it is currently stored as.
+    pub(crate) fn format_for(&self, existing: &Option<Existing>) -> Format {
+        match existing {
+            Some(existing) if !self.persist.convert => existing.format(),
+            _ => self.persist.format,
+        }
+    }
+
+    /// List previous generations of this slot, newest first.
+    ///
+    /// Generations are only kept if a [`Backups`](crate::Backups) policy is configured.
+    pub fn backups(&self) -> Result<Vec<Backup>> {
+        let storage = self.persist.storage()?;
+        backup::list(&*storage, &self.backup_dir()?)
+    }
+
+    /// Replace this slot's state with that of a backup.
+    ///
+    /// The state being replaced is itself backed up first, so a restore can be undone.
+    pub fn restore(&self, backup: &Backup) -> Result<()> {
+        let storage = self.persist.storage()?;
+        let _lock = storage.lock(&self.lock_key()?, LockMode::Exclusive)?;
+        let bytes = storage.read(&backup.key)?.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no backup at {}", backup.key),
+            )
+        })?;
+
+        let (timestamp, version) = match backup.format.deserialize::<Abseil<IgnoredAny>>(&bytes) {
+            Ok(document) => (document.timestamp, document.version),
+            Err(_) => (backup.created, migrate::FIRST_VERSION),
+        };
+
+        let existing = self.find(&*storage)?;
+        let write = self.prepare(
+            &*storage,
+            existing,
+            backup.format,
+            bytes,
+            timestamp,
+            version,
+        )?;
+        apply(self.persist, &*storage, &[write])
+    }
+
+    /// Back up `existing` and work out what needs writing to replace it with `bytes`.
+    ///
+    /// Nothing is written to the slot itself until the result is passed to [`apply`].
+    pub(crate) fn prepare(
+        &self,
+        storage: &dyn Storage,
+        existing: Option<Existing>,
+        format: Format,
+        bytes: Vec<u8>,
+        timestamp: DateTime<Utc>,
+        version: u32,
+    ) -> Result<Write> {
+        let key = self.key(format)?;
+
+        if let Some(existing) = &existing {
+            backup::rotate(
+                storage,
+                self.persist.backups,
+                &self.backup_dir()?,
+                existing.format(),
+                &existing.bytes,
+            )?;
+        }
+
+        Ok(Write {
+            stale: existing
+                .map(|existing| existing.key)
+                .filter(|stale| *stale != key),
+            key,
+            bytes,
+            timestamp,
+            version,
+        })
+    }
+
+    /// Remove this slot's file, returning `false` if there was nothing to remove.
+    pub fn delete(&self) -> Result<bool> {
+        let storage = self.persist.storage()?;
+        let _lock = storage.lock(&self.lock_key()?, LockMode::Exclusive)?;
+        let mut removed = false;
+        for (key, _) in self.candidates()? {
+            removed |= storage.delete(&key)?;
+        }
+        Ok(removed)
+    }
+
+    /// Locate the file currently backing this slot.
+    ///
+    /// A file with the configured format's extension is preferred; otherwise the extensions of
+    /// the other enabled formats are tried in turn. Files that don't parse as the format their
+    /// extension suggests are sniffed, as with hand-converted files or files written by versions
+    /// of this crate which always used `.json`.
+    pub(crate) fn find(&self, storage: &dyn Storage) -> Result<Option<Existing>> {
+        for (key, format) in self.candidates()? {
+            if let Some(bytes) = storage.read(&key)? {
+                return Ok(Some(Existing {
+                    key,
+                    hint: format,
+                    bytes,
+                    format: OnceCell::new(),
+                }));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Every key this slot's document may be stored under, in order of preference, along with
+    /// the format to try first when reading it.
+    pub(crate) fn candidates(&self) -> Result<Vec<(String, Format)>> {
+        let configured = self.persist.format;
+        let mut extensions: Vec<_> = std::iter::once(configured)
+            .chain(Format::ALL.iter().copied().filter(|&f| f != configured))
+            .map(|format| (format.extension(), format))
+            .collect();
+        if !extensions
+            .iter()
+            .any(|&(extension, _)| extension == LEGACY_EXTENSION)
+        {
+            extensions.push((LEGACY_EXTENSION, configured));
+        }
+
+        let stem = self.stem()?;
+        Ok(extensions
+            .into_iter()
+            .map(|(extension, format)| (format!("{stem}.{extension}"), format))
+            .collect())
+    }
+
+    pub(crate) fn key(&self, format: Format) -> Result<String> {
+        Ok(format!("{}.{}", self.stem()?, format.extension()))
+    }
+
+    pub(crate) fn lock_key(&self) -> Result<String> {
+        Ok(format!("{}.{LOCK_EXTENSION}", self.stem()?))
+    }
+
+    fn backup_dir(&self) -> Result<String> {
+        Ok(format!("{BACKUP_DIR}/{}/", self.stem()?))
+    }
+
+    fn stem(&self) -> Result<String> {
+        if self.name.is_empty() {
+            return Err(Error::InvalidSlot(self.name.clone()));
+        }
+        Ok(encode(&self.name))
+    }
+}
+
+/// The file currently backing a slot.
+pub(crate) struct Existing {
+    pub(crate) key: String,
+    /// The format its key suggests, which is right unless the file was converted by hand.
+    hint: Format,
+    pub(crate) bytes: Vec<u8>,
+    format: OnceCell<Format>,
+}
+
+impl Existing {
+    /// The format the file is actually in, sniffed the first time it's needed.
+    pub(crate) fn format(&self) -> Format {
+        *self
+            .format
+            .get_or_init(|| Format::detect(&self.bytes, self.hint))
+    }
+
+    /// Parse the file as its hinted format, which saves sniffing it if that works.
+    fn parse_hinted<D: for<'de> Deserialize<'de>>(&self) -> Option<D> {
+        let parsed = self.hint.deserialize(&self.bytes).ok()?;
+        // Sniffing settles on the hint whenever an envelope parses as it.
+        let _ = self.format.set(self.hint);
+        Some(parsed)
+    }
+}
+
+/// The result of reading a slot, or the corrupt file the [`Recovery`] policy says to quarantine.
+enum Read<R> {
+    Done(R),
+    Corrupt(Existing, Error),
+}
+
+impl<R> Read<R> {
+    /// Quarantine the corrupt file, if there was one, and recover with `recovered`. The caller
+    /// must hold the slot's exclusive lock.
+    fn or_quarantine(
+        self,
+        storage: &dyn Storage,
+        recovered: impl FnOnce(String, Error) -> R,
+    ) -> Result<R> {
+        match self {
+            Read::Done(result) => Ok(result),
+            Read::Corrupt(existing, e) => {
+                let quarantined = recovery::quarantine(storage, &existing.key, &existing.bytes)?;
+                Ok(recovered(quarantined, e))
+            }
+        }
+    }
+}
+
+/// A write to a slot, prepared while holding its lock.
+pub(crate) struct Write {
+    key: String,
+    bytes: Vec<u8>,
+    timestamp: DateTime<Utc>,
+    version: u32,
+    /// The key the slot was stored under before, if it is changing.
+    stale: Option<String>,
+}
+
+/// Keep the comments and layout of what's stored in `bytes`, where the format allows.
+pub(crate) fn preserve(existing: &Option<Existing>, format: Format, bytes: Vec<u8>) -> Vec<u8> {
+    match existing {
+        Some(existing) if existing.format() == format => format.preserve(&existing.bytes, bytes),
+        _ => bytes,
+    }
+}
+
+/// Write the documents in `writes` together, then remove whatever they replace.
+#[cfg_attr(not(feature = "watch"), allow(unused_variables))]
+pub(crate) fn apply(persist: &Persist, storage: &dyn Storage, writes: &[Write]) -> Result<()> {
+    // Recorded beforehand, as a watcher may see the change before the write returns.
+    #[cfg(feature = "watch")]
+    for write in writes {
+        crate::watch::record(persist, &write.key, &write.bytes)?;
+    }
+
+    let entries: Vec<_> = writes
+        .iter()
+        .map(|write| Entry {
+            key: &write.key,
+            bytes: &write.bytes,
+            timestamp: write.timestamp,
+            version: write.version,
+        })
+        .collect();
+    storage.write_entries(&entries)?;
+
+    // Whatever was there before now lives under another name; remove it so it can't be picked
+    // up in place of what we just wrote.
+    for stale in writes.iter().filter_map(|write| write.stale.as_deref()) {
+        storage.delete(stale)?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn digest(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// List the names of all slots stored by `persist`.
+pub(crate) fn list(persist: &Persist) -> Result<Vec<String>> {
+    let mut slots: Vec<_> = persist
+        .storage()?
+        .list("")?
+        .into_iter()
+        .filter_map(|name| {
+            let (stem, extension) = name.rsplit_once('.')?;
+            if extension != LEGACY_EXTENSION {
+                Format::from_extension(extension)?;
+            }
+            decode(stem)
+        })
+        .collect();
+
+    slots.sort();
+    slots.dedup();
+    Ok(slots)
+}
+
+/// Escape a slot name into something usable as a file stem on every platform.
+///
+/// Bytes other than lowercase ASCII letters, digits, `-` and `_` are percent-encoded. This keeps
+/// the mapping reversible, keeps names differing only in case apart on case-insensitive
+/// filesystems, and guarantees the stem never starts with a dot, which is reserved for
+/// temporary files.
+fn encode(name: &str) -> String {
+    let mut encoded = String::with_capacity(name.len());
+
+    for (idx, byte) in name.bytes().enumerate() {
+        let reserved = idx == 0 && RESERVED.contains(&name.to_ascii_lowercase().as_str());
+        if !reserved && is_plain(byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+
+    encoded
+}
+
+fn decode(stem: &str) -> Option<String> {
+    if stem.is_empty() {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(stem.len());
+    let mut rest = stem.as_bytes();
+
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else if is_plain(byte) {
+            bytes.push(byte);
+            rest = tail;
+        } else {
+            return None;
+        }
+    }
+
+    String::from_utf8(bytes).ok()
+}
+
+/// Whether `byte` is left as is in a file stem.
+fn is_plain(byte: u8) -> bool {
+    byte.is_ascii_lowercase() || byte.is_ascii_digit() || byte == b'-' || byte == b'_'
+}
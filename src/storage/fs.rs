@@ -0,0 +1,276 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use super::{Lock, LockMode, Storage};
+
+/// Storage in a directory on the local filesystem, with each key mapping to a file.
+///
+/// Writes go through a temporary file which is synced and renamed into place, and locks are
+/// advisory locks shared with other processes.
+#[derive(Debug, Clone)]
+pub struct FileStorage {
+    root: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        let mut path = self.root.clone();
+        path.extend(key.split('/').filter(|part| !part.is_empty()));
+        path
+    }
+}
+
+impl Storage for FileStorage {
+    fn read(&self, key: &str) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(self.path(key)) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn write(&self, key: &str, bytes: &[u8]) -> io::Result<()> {
+        let path = self.path(key);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        write_atomic(&path, bytes)
+    }
+
+    fn delete(&self, key: &str) -> io::Result<bool> {
+        match fs::remove_file(self.path(key)) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn list(&self, dir: &str) -> io::Result<Vec<String>> {
+        let entries = match fs::read_dir(self.path(dir)) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut names = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            if !entry.file_type()?.is_file() {
+                continue;
+            }
+
+            // Hidden files are locks or temporary files from in-progress writes, not values.
+            if let Some(name) = entry.file_name().to_str().filter(|n| !n.starts_with('.')) {
+                names.push(name.to_owned());
+            }
+        }
+
+        Ok(names)
+    }
+
+    /// Locks are held on a hidden file of their own rather than on the state itself, since
+    /// atomic writes replace the state file (and with it any lock held on it).
+    fn lock(&self, key: &str, mode: LockMode) -> io::Result<Lock> {
+        let path = self.path(key);
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let path = path.with_file_name(format!(".{name}"));
+        if let Some(dir) = path.parent() {
+            if !dir.exists() {
+                // Nothing can be read from a directory that doesn't exist, so there's nothing
+                // to guard; only writers need to create it.
+                if mode == LockMode::Shared {
+                    return Ok(Lock::none());
+                }
+                fs::create_dir_all(dir)?;
+            }
+        }
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        match mode {
+            LockMode::Shared => file.lock_shared()?,
+            LockMode::Exclusive => file.lock()?,
+        }
+
+        Ok(Lock::new(file))
+    }
+}
+
+/// Distinguishes the temporary files of concurrent writes made by this process.
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Write `contents` to `path` such that a crash at any point leaves either the old file or the
+/// new one, never a partial write.
+///
+/// The data is written to a temporary file in the same directory, flushed to disk and then
+/// renamed over the target. Finally, the directory itself is synced so that the rename survives
+/// a power loss.
+fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let counter = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let temp = dir.join(format!(".{name}.{}.{counter}.tmp", process::id()));
+
+    let result = (|| {
+        let mut file = File::create(&temp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::rename(&temp, path)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+        return result;
+    }
+
+    sync_dir(dir)
+}
+
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> io::Result<()> {
+    // Directory handles can't be synced on this platform; the rename itself is still atomic.
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicBool, Ordering},
+        thread,
+        time::Duration,
+    };
+
+    use super::*;
+
+    fn entries(dir: &Path) -> Vec<String> {
+        let mut names: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn write_atomic_replaces_the_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+
+        write_atomic(&path, b"first").unwrap();
+        write_atomic(&path, b"second").unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"second");
+        assert_eq!(entries(dir.path()), ["state.json"]);
+    }
+
+    #[test]
+    fn write_atomic_cleans_up_after_a_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+
+        // A non-empty directory can't be renamed over.
+        fs::create_dir(&path).unwrap();
+        fs::write(path.join("inside"), b"").unwrap();
+
+        assert!(write_atomic(&path, b"state").is_err());
+        assert_eq!(entries(dir.path()), ["state.json"]);
+        assert!(path.is_dir());
+    }
+
+    #[test]
+    fn concurrent_writes_use_separate_temporary_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+
+        thread::scope(|scope| {
+            for writer in 0..8u8 {
+                let path = &path;
+                scope.spawn(move || {
+                    for _ in 0..50 {
+                        write_atomic(path, &[writer; 64]).unwrap();
+                    }
+                });
+            }
+        });
+
+        // Whichever write landed last, it landed whole.
+        let contents = fs::read(&path).unwrap();
+        assert_eq!(contents.len(), 64);
+        assert!(contents.iter().all(|&byte| byte == contents[0]));
+        assert_eq!(entries(dir.path()), ["state.json"]);
+    }
+
+    #[test]
+    fn list_skips_hidden_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FileStorage::new(dir.path());
+
+        storage.write("a.json", b"{}").unwrap();
+        let _lock = storage.lock("a.lock", LockMode::Exclusive).unwrap();
+        fs::write(dir.path().join(".a.json.1.0.tmp"), b"").unwrap();
+
+        assert_eq!(storage.list("").unwrap(), ["a.json"]);
+        assert_eq!(storage.read("a.json").unwrap().as_deref(), Some(&b"{}"[..]));
+        assert_eq!(storage.read("b.json").unwrap(), None);
+    }
+
+    #[test]
+    fn exclusive_locks_wait_for_each_other() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FileStorage::new(dir.path());
+        let acquired = AtomicBool::new(false);
+
+        let lock = storage.lock("a.lock", LockMode::Exclusive).unwrap();
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                let _lock = storage.lock("a.lock", LockMode::Exclusive).unwrap();
+                acquired.store(true, Ordering::SeqCst);
+            });
+
+            thread::sleep(Duration::from_millis(100));
+            assert!(!acquired.load(Ordering::SeqCst));
+            drop(lock);
+        });
+        assert!(acquired.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn shared_locks_coexist() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FileStorage::new(dir.path());
+
+        let _first = storage.lock("a.lock", LockMode::Shared).unwrap();
+        let _second = storage.lock("a.lock", LockMode::Shared).unwrap();
+    }
+
+    #[test]
+    fn shared_locks_need_no_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FileStorage::new(dir.path().join("missing"));
+
+        let _lock = storage.lock("a.lock", LockMode::Shared).unwrap();
+        assert!(!dir.path().join("missing").exists());
+    }
+}
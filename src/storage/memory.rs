@@ -0,0 +1,146 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+};
+
+use super::{Lock, LockMode, Storage};
+
+/// Storage held entirely in memory, for tests.
+///
+/// Clones share the same contents, so a test can keep a handle to inspect or tamper with what
+/// a [`Persist`](crate::Persist) has stored. Keys are the names files would have on disk, e.g.
+/// `persist.json`. Locks only coordinate between users of the same process.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStorage {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    values: Mutex<BTreeMap<String, Vec<u8>>>,
+    /// Per-key lock state: the number of shared holders, or `-1` while held exclusively.
+    locks: Mutex<HashMap<String, isize>>,
+    released: Condvar,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The value stored under `key`, if any.
+    pub fn get(&self, key: &str) -> Option<Vec<u8>> {
+        self.values().get(key).cloned()
+    }
+
+    /// Store a value directly, bypassing serialization entirely.
+    pub fn insert(&self, key: impl Into<String>, bytes: impl Into<Vec<u8>>) {
+        self.values().insert(key.into(), bytes.into());
+    }
+
+    /// Remove the value stored under `key`, returning it if there was one.
+    pub fn remove(&self, key: &str) -> Option<Vec<u8>> {
+        self.values().remove(key)
+    }
+
+    /// Every key with a value, in order.
+    pub fn keys(&self) -> Vec<String> {
+        self.values().keys().cloned().collect()
+    }
+
+    fn values(&self) -> MutexGuard<'_, BTreeMap<String, Vec<u8>>> {
+        self.inner
+            .values
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn read(&self, key: &str) -> io::Result<Option<Vec<u8>>> {
+        Ok(self.get(key))
+    }
+
+    fn write(&self, key: &str, bytes: &[u8]) -> io::Result<()> {
+        self.insert(key, bytes);
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> io::Result<bool> {
+        Ok(self.remove(key).is_some())
+    }
+
+    fn list(&self, dir: &str) -> io::Result<Vec<String>> {
+        Ok(self
+            .values()
+            .keys()
+            .filter_map(|key| key.strip_prefix(dir))
+            .filter(|name| !name.contains('/'))
+            .map(str::to_owned)
+            .collect())
+    }
+
+    fn lock(&self, key: &str, mode: LockMode) -> io::Result<Lock> {
+        let mut locks = self
+            .inner
+            .locks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        loop {
+            let holders = locks.entry(key.to_owned()).or_default();
+            match mode {
+                LockMode::Shared if *holders >= 0 => {
+                    *holders += 1;
+                    break;
+                }
+                LockMode::Exclusive if *holders == 0 => {
+                    *holders = -1;
+                    break;
+                }
+                _ => {
+                    locks = self
+                        .inner
+                        .released
+                        .wait(locks)
+                        .unwrap_or_else(PoisonError::into_inner);
+                }
+            }
+        }
+
+        Ok(Lock::new(MemoryLock {
+            inner: self.inner.clone(),
+            key: key.to_owned(),
+        }))
+    }
+}
+
+struct MemoryLock {
+    inner: Arc<Inner>,
+    key: String,
+}
+
+impl Drop for MemoryLock {
+    fn drop(&mut self) {
+        let mut locks = self
+            .inner
+            .locks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        if let Some(holders) = locks.get_mut(&self.key) {
+            if *holders > 0 {
+                *holders -= 1;
+            } else {
+                *holders = 0;
+            }
+
+            if *holders == 0 {
+                locks.remove(&self.key);
+            }
+        }
+
+        self.inner.released.notify_all();
+    }
+}
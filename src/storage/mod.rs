@@ -0,0 +1,99 @@
+use std::{any::Any, fmt, io};
+
+use chrono::{DateTime, Utc};
+
+mod fs;
+mod memory;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+#[cfg(feature = "sqlite")]
+pub use self::sqlite::SqliteStorage;
+pub use self::{fs::FileStorage, memory::MemoryStorage};
+
+/// Where a [`Persist`](crate::Persist) keeps its bytes.
+///
+/// Everything is addressed by key: a relative, `/`-separated path such as `persist.json` or
+/// `backups/persist/20240101T000000000000000Z.json`. Storage knows nothing about formats or the
+/// [`Abseil`](crate::Abseil) envelope; it only moves bytes around, so an implementation backed
+/// by a database or a remote service sees exactly the same documents as the filesystem would.
+///
+/// The default is [`FileStorage`] in the directory selected on the
+/// [`PersistBuilder`](crate::PersistBuilder); use
+/// [`PersistBuilder::with_storage`](crate::PersistBuilder::with_storage) to supply another.
+pub trait Storage: fmt::Debug + Send + Sync {
+    /// Read the value stored under `key`, if any.
+    fn read(&self, key: &str) -> io::Result<Option<Vec<u8>>>;
+
+    /// Replace the value stored under `key`. Readers must see either the old value or the new
+    /// one in full, never a partial write.
+    fn write(&self, key: &str, bytes: &[u8]) -> io::Result<()>;
+
+    /// Write several slot documents at once.
+    ///
+    /// Storage that supports transactions should apply either all of `entries` or none of
+    /// them. By default they are passed to [`Storage::write`] one at a time.
+    fn write_entries(&self, entries: &[Entry<'_>]) -> io::Result<()> {
+        for entry in entries {
+            self.write(entry.key, entry.bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Remove the value stored under `key`, returning `false` if there was none.
+    fn delete(&self, key: &str) -> io::Result<bool>;
+
+    /// List the names of values directly within `dir`, which is either empty for the top level
+    /// or a key prefix ending in `/`. Values nested further down are not included.
+    fn list(&self, dir: &str) -> io::Result<Vec<String>>;
+
+    /// Wait for a lock on `key`, which is held until the returned [`Lock`] is dropped.
+    ///
+    /// Lock keys name locks, not values: they never appear in [`Storage::list`] or
+    /// [`Storage::read`], and implementations needn't store anything under them.
+    fn lock(&self, key: &str, mode: LockMode) -> io::Result<Lock>;
+}
+
+/// A slot document being written, along with the details of its [`Abseil`](crate::Abseil)
+/// envelope for storage that keeps them separately.
+#[derive(Debug, Clone, Copy)]
+pub struct Entry<'a> {
+    pub key: &'a str,
+    pub bytes: &'a [u8],
+    pub timestamp: DateTime<Utc>,
+    pub version: u32,
+}
+
+/// The kind of access a [`Lock`] guards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    /// Allows other shared locks, but excludes exclusive ones.
+    Shared,
+    /// Excludes all other locks.
+    Exclusive,
+}
+
+/// A held lock, released when dropped.
+pub struct Lock {
+    _guard: Option<Box<dyn Any + Send>>,
+}
+
+impl Lock {
+    /// Wrap a guard which releases the lock when it is dropped.
+    pub fn new(guard: impl Any + Send) -> Self {
+        Lock {
+            _guard: Some(Box::new(guard)),
+        }
+    }
+
+    /// A lock which guards nothing, for when there is nothing to guard.
+    pub fn none() -> Self {
+        Lock { _guard: None }
+    }
+}
+
+impl fmt::Debug for Lock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Lock").finish_non_exhaustive()
+    }
+}
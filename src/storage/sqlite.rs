@@ -0,0 +1,164 @@
+use std::{
+    fs, io,
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::{Entry, FileStorage, Lock, LockMode, MemoryStorage, Storage};
+
+/// How long to wait for another connection to finish writing before giving up.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(30);
+
+const SCHEMA: &str = "
+    PRAGMA journal_mode = WAL;
+    PRAGMA synchronous = FULL;
+    CREATE TABLE IF NOT EXISTS documents (
+        key TEXT PRIMARY KEY NOT NULL,
+        value BLOB NOT NULL,
+        timestamp TEXT,
+        version INTEGER
+    );
+";
+
+/// Storage in a single SQLite database, with each key mapping to a row.
+///
+/// Slot documents have their timestamp and version stored in columns of their own, so they can
+/// be queried without decoding the document. Writes of several slot documents at once, such as
+/// those made by [`Batch::commit`](crate::Batch::commit), happen in a single transaction; other
+/// writes and deletions, including those of backups, are committed individually.
+///
+/// Locks are advisory locks on hidden files next to the database, so they are shared with
+/// other processes using the same database.
+#[derive(Debug)]
+pub struct SqliteStorage {
+    connection: Mutex<Connection>,
+    locks: Arc<dyn Storage>,
+}
+
+impl SqliteStorage {
+    /// Open the database at `path`, creating it and its directory if necessary.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        fs::create_dir_all(dir)?;
+
+        let connection = Connection::open(path).map_err(io::Error::other)?;
+        Self::with_connection(connection, Arc::new(FileStorage::new(dir)))
+    }
+
+    /// Open a database which lives only as long as this storage, for tests.
+    ///
+    /// Locks only coordinate between users of the same process.
+    pub fn open_in_memory() -> io::Result<Self> {
+        let connection = Connection::open_in_memory().map_err(io::Error::other)?;
+        Self::with_connection(connection, Arc::new(MemoryStorage::new()))
+    }
+
+    fn with_connection(connection: Connection, locks: Arc<dyn Storage>) -> io::Result<Self> {
+        connection
+            .busy_timeout(BUSY_TIMEOUT)
+            .and_then(|()| connection.execute_batch(SCHEMA))
+            .map_err(io::Error::other)?;
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+            locks,
+        })
+    }
+
+    fn connection(&self) -> MutexGuard<'_, Connection> {
+        // The connection is left consistent by every statement, so a panic elsewhere doesn't
+        // make it unusable.
+        self.connection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn read(&self, key: &str) -> io::Result<Option<Vec<u8>>> {
+        self.connection()
+            .query_row(
+                "SELECT value FROM documents WHERE key = ?1",
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(io::Error::other)
+    }
+
+    /// Values written without an [`Entry`], such as backups, have no timestamp or version.
+    fn write(&self, key: &str, bytes: &[u8]) -> io::Result<()> {
+        self.connection()
+            .execute(
+                "INSERT INTO documents (key, value, timestamp, version)
+                 VALUES (?1, ?2, NULL, NULL)
+                 ON CONFLICT (key) DO UPDATE
+                 SET value = excluded.value, timestamp = NULL, version = NULL",
+                params![key, bytes],
+            )
+            .map_err(io::Error::other)?;
+        Ok(())
+    }
+
+    fn write_entries(&self, entries: &[Entry<'_>]) -> io::Result<()> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction().map_err(io::Error::other)?;
+
+        for entry in entries {
+            transaction
+                .execute(
+                    "INSERT INTO documents (key, value, timestamp, version)
+                     VALUES (?1, ?2, ?3, ?4)
+                     ON CONFLICT (key) DO UPDATE
+                     SET value = excluded.value,
+                         timestamp = excluded.timestamp,
+                         version = excluded.version",
+                    params![
+                        entry.key,
+                        entry.bytes,
+                        entry.timestamp.to_rfc3339(),
+                        entry.version,
+                    ],
+                )
+                .map_err(io::Error::other)?;
+        }
+
+        transaction.commit().map_err(io::Error::other)
+    }
+
+    fn delete(&self, key: &str) -> io::Result<bool> {
+        let deleted = self
+            .connection()
+            .execute("DELETE FROM documents WHERE key = ?1", params![key])
+            .map_err(io::Error::other)?;
+        Ok(deleted > 0)
+    }
+
+    fn list(&self, dir: &str) -> io::Result<Vec<String>> {
+        let connection = self.connection();
+        let mut statement = connection
+            .prepare("SELECT key FROM documents WHERE substr(key, 1, length(?1)) = ?1")
+            .map_err(io::Error::other)?;
+        let keys = statement
+            .query_map(params![dir], |row| row.get::<_, String>(0))
+            .map_err(io::Error::other)?;
+
+        let mut names = Vec::new();
+        for key in keys {
+            let key = key.map_err(io::Error::other)?;
+            if let Some(name) = key.strip_prefix(dir).filter(|name| !name.contains('/')) {
+                names.push(name.to_owned());
+            }
+        }
+
+        Ok(names)
+    }
+
+    fn lock(&self, key: &str, mode: LockMode) -> io::Result<Lock> {
+        self.locks.lock(key, mode)
+    }
+}
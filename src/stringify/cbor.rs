@@ -0,0 +1,40 @@
+use core::fmt;
+use std::io;
+
+use either::Either;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+pub const EXTENSION: &str = "cbor";
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub struct Error(Either<ciborium::de::Error<io::Error>, ciborium::ser::Error<io::Error>>);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            Either::Left(e) => e.fmt(f),
+            Either::Right(e) => e.fmt(f),
+        }
+    }
+}
+
+pub fn to_vec(value: &impl Serialize) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(value, &mut bytes).map_err(|e| Error(Either::Right(e)))?;
+    Ok(bytes)
+}
+
+pub fn from_slice<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    ciborium::from_reader(bytes).map_err(|e| Error(Either::Left(e)))
+}
+
+/// Decodes into an intermediate value first, so `T` can only borrow through types like `Cow`.
+pub fn from_slice_borrowed<'a, T: Deserialize<'a>>(bytes: &'a [u8]) -> Result<T> {
+    let value: ciborium::Value = from_slice(bytes)?;
+    value.deserialized().map_err(|e| {
+        let ciborium::value::Error::Custom(message) = e;
+        Error(Either::Left(ciborium::de::Error::Semantic(None, message)))
+    })
+}
@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+pub const EXTENSION: &str = "json";
+
+pub type Result<T> = serde_json::Result<T>;
+
+pub type Error = serde_json::Error;
+
+pub fn to_string(value: &impl Serialize) -> Result<String> {
+    serde_json::to_string(value)
+}
+
+pub fn to_string_pretty(value: &impl Serialize) -> Result<String> {
+    serde_json::to_string_pretty(value)
+}
+
+pub fn from_str<'a, T: Deserialize<'a>>(s: &'a str) -> Result<T> {
+    serde_json::from_str(s)
+}
@@ -0,0 +1,270 @@
+use std::{fmt, str};
+
+use serde::{
+    de::{DeserializeOwned, IgnoredAny},
+    Deserialize, Serialize,
+};
+
+use crate::Abseil;
+
+#[cfg(feature = "cbor")]
+mod cbor;
+#[cfg(feature = "json")]
+mod json;
+#[cfg(feature = "msgpack")]
+mod msgpack;
+#[cfg(feature = "ron")]
+mod ron;
+#[cfg(feature = "toml")]
+mod toml;
+#[cfg(feature = "yaml")]
+mod yaml;
+
+#[cfg(not(any(
+    feature = "cbor",
+    feature = "json",
+    feature = "msgpack",
+    feature = "ron",
+    feature = "toml",
+    feature = "yaml"
+)))]
+compile_error!(
+    "abseil requires at least one format feature: `cbor`, `json`, `msgpack`, `ron`, `toml` or `yaml`"
+);
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// A text format was asked to read something that isn't valid UTF-8.
+    Utf8(str::Utf8Error),
+    #[cfg(feature = "cbor")]
+    Cbor(cbor::Error),
+    #[cfg(feature = "json")]
+    Json(json::Error),
+    #[cfg(feature = "msgpack")]
+    MessagePack(msgpack::Error),
+    #[cfg(feature = "ron")]
+    Ron(ron::Error),
+    #[cfg(feature = "toml")]
+    Toml(toml::Error),
+    #[cfg(feature = "yaml")]
+    Yaml(yaml::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Utf8(e) => e.fmt(f),
+            #[cfg(feature = "cbor")]
+            Error::Cbor(e) => e.fmt(f),
+            #[cfg(feature = "json")]
+            Error::Json(e) => e.fmt(f),
+            #[cfg(feature = "msgpack")]
+            Error::MessagePack(e) => e.fmt(f),
+            #[cfg(feature = "ron")]
+            Error::Ron(e) => e.fmt(f),
+            #[cfg(feature = "toml")]
+            Error::Toml(e) => e.fmt(f),
+            #[cfg(feature = "yaml")]
+            Error::Yaml(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// The serialization format used for a [`Persist`](crate::Persist)'s files.
+///
+/// Every format enabled through cargo features is available at runtime. The default is json
+/// when that feature is enabled, and otherwise the first enabled format in [`Format::ALL`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    #[cfg(feature = "json")]
+    Json,
+    #[cfg(feature = "ron")]
+    Ron,
+    #[cfg(feature = "toml")]
+    Toml,
+    #[cfg(feature = "yaml")]
+    Yaml,
+    #[cfg(feature = "cbor")]
+    Cbor,
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+}
+
+impl Format {
+    /// Every format compiled into this build: text formats first, then binary formats.
+    pub const ALL: &'static [Format] = &[
+        #[cfg(feature = "json")]
+        Format::Json,
+        #[cfg(feature = "ron")]
+        Format::Ron,
+        #[cfg(feature = "toml")]
+        Format::Toml,
+        #[cfg(feature = "yaml")]
+        Format::Yaml,
+        #[cfg(feature = "cbor")]
+        Format::Cbor,
+        #[cfg(feature = "msgpack")]
+        Format::MessagePack,
+    ];
+
+    /// The format conventionally associated with a file extension, if it is enabled.
+    pub fn from_extension(extension: &str) -> Option<Format> {
+        Format::ALL
+            .iter()
+            .copied()
+            .find(|format| format.extension().eq_ignore_ascii_case(extension))
+    }
+
+    /// Work out which format `bytes` are written in.
+    ///
+    /// `hint` (usually derived from the file extension) wins if the bytes parse as an
+    /// [`Abseil`] envelope in that format. Failing that, the first enabled format that accepts
+    /// them is chosen. If nothing accepts them, the hint is returned so that parsing reports
+    /// errors in the expected format.
+    pub(crate) fn detect(bytes: &[u8], hint: Format) -> Format {
+        // Checking for the envelope rather than any value at all matters for the binary
+        // formats, which will happily decode the first byte of a text file as an integer.
+        let parses = |format: Format| format.deserialize::<Abseil<IgnoredAny>>(bytes).is_ok();
+
+        if parses(hint) {
+            return hint;
+        }
+
+        Format::ALL
+            .iter()
+            .copied()
+            .find(|&format| format != hint && parses(format))
+            .unwrap_or(hint)
+    }
+
+    /// The file extension used for documents in this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            #[cfg(feature = "json")]
+            Format::Json => json::EXTENSION,
+            #[cfg(feature = "ron")]
+            Format::Ron => ron::EXTENSION,
+            #[cfg(feature = "toml")]
+            Format::Toml => toml::EXTENSION,
+            #[cfg(feature = "yaml")]
+            Format::Yaml => yaml::EXTENSION,
+            #[cfg(feature = "cbor")]
+            Format::Cbor => cbor::EXTENSION,
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => msgpack::EXTENSION,
+        }
+    }
+
+    /// Whether this format produces binary rather than human-readable output.
+    ///
+    /// Binary formats have no pretty form, so the `pretty` setting doesn't apply to them.
+    pub fn is_binary(self) -> bool {
+        match self {
+            #[cfg(feature = "cbor")]
+            Format::Cbor => true,
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => true,
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+
+    /// Binary formats ignore `pretty`.
+    #[cfg_attr(
+        not(any(feature = "json", feature = "ron", feature = "toml", feature = "yaml")),
+        allow(unused_variables)
+    )]
+    pub(crate) fn serialize(self, value: &impl Serialize, pretty: bool) -> Result<Vec<u8>> {
+        match self {
+            #[cfg(feature = "json")]
+            Format::Json if pretty => bytes(json::to_string_pretty(value)).map_err(Error::Json),
+            #[cfg(feature = "json")]
+            Format::Json => bytes(json::to_string(value)).map_err(Error::Json),
+            #[cfg(feature = "ron")]
+            Format::Ron if pretty => bytes(ron::to_string_pretty(value)).map_err(Error::Ron),
+            #[cfg(feature = "ron")]
+            Format::Ron => bytes(ron::to_string(value)).map_err(Error::Ron),
+            #[cfg(feature = "toml")]
+            Format::Toml if pretty => bytes(toml::to_string_pretty(value)).map_err(Error::Toml),
+            #[cfg(feature = "toml")]
+            Format::Toml => bytes(toml::to_string(value)).map_err(Error::Toml),
+            #[cfg(feature = "yaml")]
+            Format::Yaml if pretty => bytes(yaml::to_string_pretty(value)).map_err(Error::Yaml),
+            #[cfg(feature = "yaml")]
+            Format::Yaml => bytes(yaml::to_string(value)).map_err(Error::Yaml),
+            #[cfg(feature = "cbor")]
+            Format::Cbor => cbor::to_vec(value).map_err(Error::Cbor),
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => msgpack::to_vec(value).map_err(Error::MessagePack),
+        }
+    }
+
+    /// Carry the comments and layout of `old`, a document in this format, over to `new`.
+    ///
+    /// Only TOML supports this. Other formats get `new` back unchanged, as does anything that
+    /// doesn't parse.
+    #[cfg_attr(not(feature = "toml"), allow(unused_variables))]
+    pub(crate) fn preserve(self, old: &[u8], new: Vec<u8>) -> Vec<u8> {
+        match self {
+            #[cfg(feature = "toml")]
+            Format::Toml => toml::preserve(old, &new).unwrap_or(new),
+            #[allow(unreachable_patterns)]
+            _ => new,
+        }
+    }
+
+    pub(crate) fn deserialize<T: DeserializeOwned>(self, bytes: &[u8]) -> Result<T> {
+        match self {
+            #[cfg(feature = "json")]
+            Format::Json => json::from_str(text(bytes)?).map_err(Error::Json),
+            #[cfg(feature = "ron")]
+            Format::Ron => ron::from_str(text(bytes)?).map_err(Error::Ron),
+            #[cfg(feature = "toml")]
+            Format::Toml => toml::from_str(text(bytes)?).map_err(Error::Toml),
+            #[cfg(feature = "yaml")]
+            Format::Yaml => yaml::from_str(text(bytes)?).map_err(Error::Yaml),
+            #[cfg(feature = "cbor")]
+            Format::Cbor => cbor::from_slice(bytes).map_err(Error::Cbor),
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => msgpack::from_slice(bytes).map_err(Error::MessagePack),
+        }
+    }
+
+    /// Like [`Format::deserialize`], but `T` may borrow from `bytes` where the format allows.
+    pub(crate) fn deserialize_borrowed<'a, T: Deserialize<'a>>(self, bytes: &'a [u8]) -> Result<T> {
+        match self {
+            #[cfg(feature = "json")]
+            Format::Json => json::from_str(text(bytes)?).map_err(Error::Json),
+            #[cfg(feature = "ron")]
+            Format::Ron => ron::from_str(text(bytes)?).map_err(Error::Ron),
+            #[cfg(feature = "toml")]
+            Format::Toml => toml::from_str(text(bytes)?).map_err(Error::Toml),
+            #[cfg(feature = "yaml")]
+            Format::Yaml => yaml::from_str(text(bytes)?).map_err(Error::Yaml),
+            #[cfg(feature = "cbor")]
+            Format::Cbor => cbor::from_slice_borrowed(bytes).map_err(Error::Cbor),
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => msgpack::from_slice(bytes).map_err(Error::MessagePack),
+        }
+    }
+}
+
+#[cfg(any(feature = "json", feature = "ron", feature = "toml", feature = "yaml"))]
+fn bytes<E>(text: Result<String, E>) -> Result<Vec<u8>, E> {
+    text.map(String::into_bytes)
+}
+
+#[cfg(any(feature = "json", feature = "ron", feature = "toml", feature = "yaml"))]
+fn text(bytes: &[u8]) -> Result<&str> {
+    str::from_utf8(bytes).map_err(Error::Utf8)
+}
+
+impl Default for Format {
+    fn default() -> Self {
+        Format::ALL[0]
+    }
+}
@@ -0,0 +1,30 @@
+use core::fmt;
+
+use either::Either;
+use serde::{Deserialize, Serialize};
+
+pub const EXTENSION: &str = "msgpack";
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub struct Error(Either<rmp_serde::decode::Error, rmp_serde::encode::Error>);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            Either::Left(e) => e.fmt(f),
+            Either::Right(e) => e.fmt(f),
+        }
+    }
+}
+
+/// Structs are written as maps rather than arrays so that documents are self-describing and
+/// survive fields being added or reordered.
+pub fn to_vec(value: &impl Serialize) -> Result<Vec<u8>> {
+    rmp_serde::to_vec_named(value).map_err(|e| Error(Either::Right(e)))
+}
+
+pub fn from_slice<'a, T: Deserialize<'a>>(bytes: &'a [u8]) -> Result<T> {
+    rmp_serde::from_slice(bytes).map_err(|e| Error(Either::Left(e)))
+}
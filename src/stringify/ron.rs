@@ -0,0 +1,33 @@
+use core::fmt;
+
+use either::Either;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+pub const EXTENSION: &str = "ron";
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub struct Error(Either<ron::error::SpannedError, ron::Error>);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            Either::Left(e) => e.fmt(f),
+            Either::Right(e) => e.fmt(f),
+        }
+    }
+}
+
+pub fn to_string(value: &impl Serialize) -> Result<String> {
+    ron::to_string(value).map_err(|e| Error(Either::Right(e)))
+}
+
+pub fn to_string_pretty(value: &impl Serialize) -> Result<String> {
+    ron::ser::to_string_pretty(value, PrettyConfig::default()).map_err(|e| Error(Either::Right(e)))
+}
+
+pub fn from_str<'a, T: Deserialize<'a>>(s: &'a str) -> Result<T> {
+    ron::from_str(s).map_err(|e| Error(Either::Left(e)))
+}
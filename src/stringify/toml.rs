@@ -0,0 +1,141 @@
+use core::{fmt, str};
+
+use either::Either;
+use serde::{Deserialize, Serialize};
+use toml_edit::{ArrayOfTables, DocumentMut, InlineTable, Item, Table, Value};
+
+pub const EXTENSION: &str = "toml";
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub struct Error(Either<toml::de::Error, toml::ser::Error>);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            Either::Left(e) => e.fmt(f),
+            Either::Right(e) => e.fmt(f),
+        }
+    }
+}
+
+pub fn to_string(value: &impl Serialize) -> Result<String> {
+    toml::to_string(value).map_err(|e| Error(Either::Right(e)))
+}
+
+pub fn to_string_pretty(value: &impl Serialize) -> Result<String> {
+    toml::to_string_pretty(value).map_err(|e| Error(Either::Right(e)))
+}
+
+/// Strings are always copied out of `s`, so `T` can only borrow through types like `Cow`.
+pub fn from_str<'a, T: Deserialize<'a>>(s: &'a str) -> Result<T> {
+    T::deserialize(toml::Deserializer::new(s)).map_err(|e| Error(Either::Left(e)))
+}
+
+/// Rewrite `old` to hold the values in `new`, keeping the comments, whitespace and key order of
+/// `old` wherever the two agree on structure.
+///
+/// Returns `None` if either document doesn't parse.
+pub fn preserve(old: &[u8], new: &[u8]) -> Option<Vec<u8>> {
+    let mut old: DocumentMut = str::from_utf8(old).ok()?.parse().ok()?;
+    let new: DocumentMut = str::from_utf8(new).ok()?.parse().ok()?;
+    merge_table(old.as_table_mut(), new.as_table());
+    Some(old.to_string().into_bytes())
+}
+
+fn merge_table(old: &mut Table, new: &Table) {
+    old.retain(|key, _| new.contains_key(key));
+    for (key, item) in new.iter() {
+        match old.get_mut(key) {
+            Some(existing) => merge_item(existing, item),
+            None => {
+                old.insert(key, detach(item));
+            }
+        }
+    }
+}
+
+fn merge_inline_table(old: &mut InlineTable, new: &InlineTable) {
+    old.retain(|key, _| new.contains_key(key));
+    for (key, value) in new.iter() {
+        match old.get_mut(key) {
+            Some(existing) => merge_value(existing, value),
+            None => {
+                old.insert(key, value.clone());
+            }
+        }
+    }
+}
+
+fn merge_item(old: &mut Item, new: &Item) {
+    match (old, new) {
+        (Item::Table(old), Item::Table(new)) => merge_table(old, new),
+        (Item::Value(Value::InlineTable(old)), Item::Table(new)) => {
+            merge_inline_table(old, &new.clone().into_inline_table())
+        }
+        (Item::Value(old), Item::Value(new)) => merge_value(old, new),
+        (Item::ArrayOfTables(old), Item::ArrayOfTables(new)) if old.len() == new.len() => {
+            for (old, new) in old.iter_mut().zip(new.iter()) {
+                merge_table(old, new);
+            }
+        }
+        (old, new) => *old = detach(new),
+    }
+}
+
+fn merge_value(old: &mut Value, new: &Value) {
+    match (old, new) {
+        (Value::InlineTable(old), Value::InlineTable(new)) => merge_inline_table(old, new),
+        (Value::Array(old), Value::Array(new)) if old.len() == new.len() => {
+            for (old, new) in old.iter_mut().zip(new.iter()) {
+                merge_value(old, new);
+            }
+        }
+        (old, new) if !same(old, new) => {
+            // Keep the comments and spacing around the value, but not how it was written.
+            let decor = old.decor().clone();
+            *old = new.clone();
+            *old.decor_mut() = decor;
+        }
+        _ => {}
+    }
+}
+
+/// Whether two scalars hold the same value, however they are written.
+fn same(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => a.value() == b.value(),
+        (Value::Integer(a), Value::Integer(b)) => a.value() == b.value(),
+        (Value::Float(a), Value::Float(b)) => a.value().to_bits() == b.value().to_bits(),
+        (Value::Boolean(a), Value::Boolean(b)) => a.value() == b.value(),
+        (Value::Datetime(a), Value::Datetime(b)) => a.value() == b.value(),
+        _ => false,
+    }
+}
+
+/// Copy an item from another document, dropping the positions its tables had there so that new
+/// tables are placed after their parents.
+fn detach(item: &Item) -> Item {
+    match item {
+        Item::Table(table) => Item::Table(detach_table(table)),
+        Item::ArrayOfTables(tables) => {
+            let mut detached = ArrayOfTables::new();
+            for table in tables.iter() {
+                detached.push(detach_table(table));
+            }
+            Item::ArrayOfTables(detached)
+        }
+        item => item.clone(),
+    }
+}
+
+fn detach_table(table: &Table) -> Table {
+    let mut detached = Table::new();
+    detached.set_implicit(table.is_implicit());
+    detached.set_dotted(table.is_dotted());
+    for (key, item) in table.iter() {
+        detached.insert(key, detach(item));
+    }
+    detached
+}
@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+pub const EXTENSION: &str = "yaml";
+
+pub type Result<T> = serde_yaml_ng::Result<T>;
+
+pub type Error = serde_yaml_ng::Error;
+
+pub fn to_string(value: &impl Serialize) -> Result<String> {
+    serde_yaml_ng::to_string(value)
+}
+
+/// YAML only has the one block style, so this is the same as [`to_string`].
+pub fn to_string_pretty(value: &impl Serialize) -> Result<String> {
+    serde_yaml_ng::to_string(value)
+}
+
+pub fn from_str<'a, T: Deserialize<'a>>(s: &'a str) -> Result<T> {
+    serde_yaml_ng::from_str(s)
+}
@@ -0,0 +1,193 @@
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    fs, io,
+    path::{self, Path, PathBuf},
+    sync::{mpsc, Mutex, PoisonError},
+    time::Duration,
+};
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Deserialize;
+
+use crate::{
+    slot::{self, Slot},
+    storage::{FileStorage, LockMode, Storage},
+    Abseil, Backend, Error, Persist, Result,
+};
+
+/// Digests of what this process last wrote to each slot's file, so that watchers can tell its
+/// own writes apart from changes made by someone else.
+static WRITTEN: Mutex<BTreeMap<PathBuf, u64>> = Mutex::new(BTreeMap::new());
+
+/// Note that `persist` is about to write `bytes` to the slot stored under `key`.
+///
+/// Only files can be watched, so writes to other storage aren't recorded.
+pub(crate) fn record(persist: &Persist, key: &str, bytes: &[u8]) -> Result<()> {
+    let Backend::Files = persist.storage else {
+        return Ok(());
+    };
+
+    let path = persist.dir(persist.location)?.join(key);
+    let path = path::absolute(&path).unwrap_or(path);
+    WRITTEN
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(path, slot::digest(bytes));
+    Ok(())
+}
+
+fn written(path: &Path, digest: u64) -> bool {
+    let path = path::absolute(path).unwrap_or_else(|_| path.to_owned());
+    WRITTEN
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&path)
+        == Some(&digest)
+}
+
+/// Changes made to a slot's file by other processes or by hand.
+///
+/// Created by [`Persist::watch`](crate::Persist::watch) or [`Slot::watch`]. Each time the file
+/// changes, it is read again and the result is delivered in order: the reloaded document, or
+/// the error that stopped it from loading, such as a typo in a hand-edited file. Writes made
+/// through any [`Persist`](crate::Persist) in this process are not reported, and the
+/// [`Recovery`](crate::Recovery) policy is not applied, so a broken file is left for the user
+/// to fix.
+///
+/// Watching stops when this is dropped.
+pub struct Watch<T> {
+    receiver: mpsc::Receiver<Result<Abseil<T>>>,
+    _watcher: RecommendedWatcher,
+}
+
+impl<T> Watch<T>
+where
+    T: for<'de> Deserialize<'de> + Send + 'static,
+{
+    pub(crate) fn new(persist: &Persist, slot: &Slot<'_>) -> Result<Self> {
+        let persist = persist.clone();
+        let name = slot.name().to_owned();
+
+        let Backend::Files = persist.storage else {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "only state stored in files can be watched",
+            )
+            .into());
+        };
+
+        let dir = persist.dir(persist.location)?;
+        fs::create_dir_all(&dir)?;
+
+        let files: Vec<_> = slot
+            .candidates()?
+            .into_iter()
+            .map(|(key, _)| OsString::from(key))
+            .collect();
+
+        // Whatever is there now has already been seen by whoever is watching.
+        let storage = FileStorage::new(&dir);
+        let mut last = slot
+            .find(&storage)?
+            .map(|existing| slot::digest(&existing.bytes));
+
+        let watched = dir.clone();
+        let (sender, receiver) = mpsc::channel();
+        let handle = move |event: notify::Result<Event>| {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    let _ = sender.send(Err(Error::IO(io::Error::other(e))));
+                    return;
+                }
+            };
+
+            let relevant = !matches!(event.kind, EventKind::Access(_))
+                && event.paths.iter().any(|path| {
+                    path.file_name()
+                        .is_some_and(|file| files.iter().any(|f| f == file))
+                });
+
+            if relevant {
+                let slot = persist.slot(&name);
+                if let Some(reloaded) = reload(&slot, &storage, &dir, &mut last).transpose() {
+                    let _ = sender.send(reloaded);
+                }
+            }
+        };
+
+        let mut watcher = notify::recommended_watcher(handle).map_err(io::Error::other)?;
+        watcher
+            .watch(&watched, RecursiveMode::NonRecursive)
+            .map_err(io::Error::other)?;
+
+        Ok(Self {
+            receiver,
+            _watcher: watcher,
+        })
+    }
+
+    /// Wait for the next change.
+    pub fn recv(&self) -> Option<Result<Abseil<T>>> {
+        self.receiver.recv().ok()
+    }
+
+    /// Wait up to `timeout` for the next change.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<Result<Abseil<T>>> {
+        self.receiver.recv_timeout(timeout).ok()
+    }
+
+    /// Take the next change if there has been one, without waiting.
+    pub fn try_recv(&self) -> Option<Result<Abseil<T>>> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+impl<T> Iterator for Watch<T>
+where
+    T: for<'de> Deserialize<'de> + Send + 'static,
+{
+    type Item = Result<Abseil<T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv()
+    }
+}
+
+impl<T> std::fmt::Debug for Watch<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Watch").finish_non_exhaustive()
+    }
+}
+
+/// Read the slot again, unless its contents are what was last seen or what this process wrote.
+fn reload<T>(
+    slot: &Slot<'_>,
+    storage: &FileStorage,
+    dir: &Path,
+    last: &mut Option<u64>,
+) -> Result<Option<Abseil<T>>>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    let _lock = storage.lock(&slot.lock_key()?, LockMode::Shared)?;
+
+    // A missing or empty file is usually the middle of an editor's save, with the new contents
+    // on their way.
+    let Some(existing) = slot
+        .find(storage)?
+        .filter(|existing| !existing.bytes.is_empty())
+    else {
+        return Ok(None);
+    };
+
+    let digest = slot::digest(&existing.bytes);
+    let seen = *last == Some(digest) || written(&dir.join(&existing.key), digest);
+    *last = Some(digest);
+    if seen {
+        return Ok(None);
+    }
+
+    slot.decode(&existing).map(Some)
+}
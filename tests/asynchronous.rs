@@ -0,0 +1,68 @@
+#![cfg(feature = "tokio")]
+
+use std::future::Future;
+
+use abseil::{Error, MemoryStorage, Outcome, Persist, Recovery};
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+fn with_recovery(storage: &MemoryStorage, recovery: Recovery) -> Persist {
+    Persist::builder("test")
+        .with_storage(storage.clone())
+        .with_recovery(recovery)
+        .build()
+}
+
+#[test]
+fn async_calls_match_their_blocking_counterparts() {
+    let storage = MemoryStorage::new();
+    let persist = with_recovery(&storage, Recovery::Fail);
+
+    block_on(async {
+        assert_eq!(persist.load_async::<u32>().await.unwrap().state, 0);
+        persist.store_async(vec![1u32]).await.unwrap();
+        let len = persist
+            .update_async(|state: &mut Vec<u32>| {
+                state.push(2);
+                state.len()
+            })
+            .await
+            .unwrap();
+        assert_eq!(len, 2);
+    });
+
+    assert_eq!(persist.load::<Vec<u32>>().unwrap().state, [1, 2]);
+}
+
+#[test]
+fn async_calls_return_the_same_errors() {
+    let storage = MemoryStorage::new();
+    let persist = with_recovery(&storage, Recovery::Fail);
+    persist.store(1u32).unwrap();
+    let key = storage.keys().pop().unwrap();
+    storage.insert(key, b"\x00\xff not a document".to_vec());
+
+    let result = block_on(persist.load_async::<u32>());
+    assert!(matches!(result, Err(Error::Serialization(_))));
+    assert!(matches!(
+        persist.load::<u32>(),
+        Err(Error::Serialization(_))
+    ));
+
+    let quarantine = with_recovery(&storage, Recovery::Quarantine);
+    let (document, outcome) = block_on(quarantine.load_with_outcome_async::<u32>()).unwrap();
+    assert_eq!(document.state, 0);
+    assert!(matches!(outcome, Outcome::Quarantined(..)));
+}
+
+#[test]
+#[should_panic = "update failed"]
+fn panics_are_raised_in_the_caller() {
+    let persist = with_recovery(&MemoryStorage::new(), Recovery::Fail);
+    let _ = block_on(persist.update_async(|_: &mut u32| panic!("update failed")));
+}
@@ -0,0 +1,104 @@
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use abseil::{Lock, LockMode, MemoryStorage, Persist, Storage};
+
+/// Memory storage which counts its writes and can be made to fail them.
+#[derive(Debug, Clone, Default)]
+struct Flaky {
+    inner: MemoryStorage,
+    writes: Arc<AtomicUsize>,
+    failing: Arc<AtomicBool>,
+}
+
+impl Storage for Flaky {
+    fn read(&self, key: &str) -> io::Result<Option<Vec<u8>>> {
+        self.inner.read(key)
+    }
+
+    fn write(&self, key: &str, bytes: &[u8]) -> io::Result<()> {
+        if self.failing.load(Ordering::SeqCst) {
+            return Err(io::Error::other("failing"));
+        }
+        self.writes.fetch_add(1, Ordering::SeqCst);
+        self.inner.write(key, bytes)
+    }
+
+    fn delete(&self, key: &str) -> io::Result<bool> {
+        self.inner.delete(key)
+    }
+
+    fn list(&self, dir: &str) -> io::Result<Vec<String>> {
+        self.inner.list(dir)
+    }
+
+    fn lock(&self, key: &str, mode: LockMode) -> io::Result<Lock> {
+        self.inner.lock(key, mode)
+    }
+}
+
+fn persist(storage: &Flaky, delay: Duration) -> Persist {
+    Persist::builder("test")
+        .with_storage(storage.clone())
+        .with_autosave_delay(delay)
+        .build()
+}
+
+#[test]
+fn bursts_of_changes_are_stored_once() {
+    let storage = Flaky::default();
+    let persist = persist(&storage, Duration::from_millis(100));
+    let autosave = persist.autosave(0u32);
+
+    for _ in 0..5 {
+        autosave.modify(|count| *count += 1);
+        thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(storage.writes.load(Ordering::SeqCst), 0);
+
+    thread::sleep(Duration::from_millis(500));
+    assert_eq!(storage.writes.load(Ordering::SeqCst), 1);
+    assert_eq!(persist.load::<u32>().unwrap().state, 5);
+
+    // Nothing is left to write on the way out.
+    drop(autosave);
+    assert_eq!(storage.writes.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn dropping_stores_pending_changes() {
+    let storage = Flaky::default();
+    let persist = persist(&storage, Duration::from_secs(60));
+
+    let autosave = persist.autosave(Vec::new());
+    autosave.modify(|items| items.push(1u32));
+    assert_eq!(autosave.read(Vec::len), 1);
+    drop(autosave);
+
+    assert_eq!(persist.load::<Vec<u32>>().unwrap().state, [1]);
+}
+
+#[test]
+fn failed_writes_are_retried() {
+    let storage = Flaky::default();
+    let persist = persist(&storage, Duration::from_millis(20));
+    let autosave = persist.autosave(0u32);
+
+    storage.failing.store(true, Ordering::SeqCst);
+    autosave.modify(|count| *count = 7);
+    assert!(autosave.flush().is_err());
+    thread::sleep(Duration::from_millis(100));
+    assert_eq!(persist.load::<u32>().unwrap().state, 0);
+
+    storage.failing.store(false, Ordering::SeqCst);
+    thread::sleep(Duration::from_millis(300));
+    assert_eq!(persist.load::<u32>().unwrap().state, 7);
+    autosave.close().unwrap();
+}
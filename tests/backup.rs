@@ -0,0 +1,64 @@
+use abseil::{Backups, MemoryStorage, Persist};
+
+fn persist(storage: &MemoryStorage, backups: Backups) -> Persist {
+    Persist::builder("test")
+        .with_storage(storage.clone())
+        .with_backups(backups)
+        .build()
+}
+
+#[test]
+fn backups_are_pruned_to_the_policy() {
+    let storage = MemoryStorage::new();
+    let persist = persist(&storage, Backups::Keep(2));
+
+    for generation in 0..5u32 {
+        persist.store(generation).unwrap();
+    }
+
+    let backups = persist.backups().unwrap();
+    assert_eq!(backups.len(), 2);
+    assert!(backups[0].created >= backups[1].created);
+    assert!(backups.iter().all(|backup| backup.timestamp.is_some()));
+}
+
+#[test]
+fn backups_are_pruned_without_being_read() {
+    let storage = MemoryStorage::new();
+    let persist = persist(&storage, Backups::Keep(1));
+    persist.store(1u32).unwrap();
+    persist.store(2u32).unwrap();
+
+    // An unreadable backup is still pruned by the stamp in its name.
+    let garbage = persist.backups().unwrap()[0].key.clone();
+    storage.insert(garbage.clone(), b"\xff".to_vec());
+    persist.store(3u32).unwrap();
+
+    let backups = persist.backups().unwrap();
+    assert_eq!(backups.len(), 1);
+    assert_ne!(backups[0].key, garbage);
+}
+
+#[test]
+fn restore_brings_back_a_previous_generation() {
+    let storage = MemoryStorage::new();
+    let persist = persist(&storage, Backups::Keep(5));
+    persist.store(1u32).unwrap();
+    persist.store(2u32).unwrap();
+
+    let backups = persist.backups().unwrap();
+    persist.restore(&backups[0]).unwrap();
+    assert_eq!(persist.load::<u32>().unwrap().state, 1);
+
+    // The state replaced by the restore is itself kept.
+    assert_eq!(persist.backups().unwrap().len(), 2);
+}
+
+#[test]
+fn nothing_is_kept_when_disabled() {
+    let storage = MemoryStorage::new();
+    let persist = persist(&storage, Backups::Disabled);
+    persist.store(1u32).unwrap();
+    persist.store(2u32).unwrap();
+    assert!(persist.backups().unwrap().is_empty());
+}
@@ -0,0 +1,85 @@
+// Setting fields relies on the default format, which is JSON when it is enabled; RON documents
+// can only be read this way.
+#![cfg(feature = "json")]
+
+use abseil::{Error, MemoryStorage, Persist};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Settings {
+    window: Window,
+    recent: Vec<String>,
+    theme: Option<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Window {
+    width: u32,
+    height: u32,
+}
+
+fn persist() -> Persist {
+    let persist = Persist::builder("test")
+        .with_storage(MemoryStorage::new())
+        .build();
+    persist
+        .store(Settings {
+            window: Window {
+                width: 800,
+                height: 600,
+            },
+            recent: vec!["a".into(), "b".into()],
+            theme: Some("dark".into()),
+        })
+        .unwrap();
+    persist
+}
+
+#[test]
+fn fields_are_read_by_path() {
+    let persist = persist();
+    assert_eq!(persist.get::<u32>("window.width").unwrap(), Some(800));
+    assert_eq!(
+        persist.get::<String>("recent.1").unwrap().as_deref(),
+        Some("b")
+    );
+    assert_eq!(
+        persist.get::<String>("theme").unwrap().as_deref(),
+        Some("dark")
+    );
+    assert_eq!(persist.get::<u32>("window.depth").unwrap(), None);
+    assert_eq!(persist.get::<String>("recent.5").unwrap(), None);
+    assert!(matches!(
+        persist.get::<String>("window.width"),
+        Err(Error::Value(_))
+    ));
+}
+
+#[test]
+fn fields_are_written_by_path() {
+    let persist = persist();
+    persist.set("window.width", 1024u32).unwrap();
+    persist.set("recent.0", "z").unwrap();
+    persist.set("theme", None::<String>).unwrap();
+
+    let settings = persist.load::<Settings>().unwrap().state;
+    assert_eq!(settings.window.width, 1024);
+    assert_eq!(settings.window.height, 600);
+    assert_eq!(settings.recent, ["z", "b"]);
+    assert_eq!(settings.theme, None);
+
+    assert!(matches!(persist.set("recent.9", "x"), Err(Error::Value(_))));
+    assert!(matches!(
+        persist.set("window..width", 1),
+        Err(Error::Value(_))
+    ));
+}
+
+#[test]
+fn setting_a_field_of_a_missing_slot_creates_it() {
+    let persist = Persist::builder("test")
+        .with_storage(MemoryStorage::new())
+        .build();
+    persist.set("window.width", 640u32).unwrap();
+    assert_eq!(persist.get::<u32>("window.width").unwrap(), Some(640));
+}
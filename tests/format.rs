@@ -0,0 +1,93 @@
+use abseil::{Format, MemoryStorage, Persist};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Settings {
+    name: String,
+    sizes: Vec<u32>,
+    theme: Option<String>,
+}
+
+#[test]
+fn every_format_round_trips() {
+    for &format in Format::ALL {
+        let storage = MemoryStorage::new();
+        let persist = Persist::builder("test")
+            .with_storage(storage.clone())
+            .with_format(format)
+            .build();
+
+        let settings = Settings {
+            name: "abseil".into(),
+            sizes: vec![1, 2, 3],
+            theme: Some("dark".into()),
+        };
+        persist.store(&settings).unwrap();
+
+        let key = format!("persist.{}", format.extension());
+        assert_eq!(storage.keys(), [key], "{format:?}");
+        assert_eq!(
+            persist.load::<Settings>().unwrap().state,
+            settings,
+            "{format:?}"
+        );
+
+        // Whatever format is configured, documents in the others are found too.
+        let other = Persist::builder("test")
+            .with_storage(storage.clone())
+            .with_format(Format::ALL[0])
+            .build();
+        assert_eq!(
+            other.load::<Settings>().unwrap().state,
+            settings,
+            "{format:?}"
+        );
+    }
+}
+
+#[test]
+fn formats_are_known_by_extension() {
+    for &format in Format::ALL {
+        assert_eq!(Format::from_extension(format.extension()), Some(format));
+    }
+    assert_eq!(Format::from_extension("txt"), None);
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn toml_comments_and_layout_are_kept() {
+    let storage = MemoryStorage::new();
+    let persist = Persist::builder("test")
+        .with_storage(storage.clone())
+        .with_format(Format::Toml)
+        .build();
+    persist
+        .store(Settings {
+            name: "abseil".into(),
+            sizes: vec![1],
+            theme: None,
+        })
+        .unwrap();
+
+    let text = String::from_utf8(storage.get("persist.toml").unwrap()).unwrap();
+    let edited = text
+        .replace("name =", "# Shown in the title bar.\nname =")
+        .replace("[state]", "\n[state]  # Everything the app keeps.");
+    storage.insert("persist.toml", edited);
+
+    persist.set("name", "renamed").unwrap();
+    persist
+        .update(|settings: &mut Settings| settings.sizes.push(2))
+        .unwrap();
+
+    let text = String::from_utf8(storage.get("persist.toml").unwrap()).unwrap();
+    assert!(
+        text.contains("# Shown in the title bar.\nname = \"renamed\""),
+        "{text}"
+    );
+    assert!(
+        text.contains("[state]  # Everything the app keeps."),
+        "{text}"
+    );
+    assert_eq!(persist.load::<Settings>().unwrap().state.sizes, [1, 2]);
+}
@@ -0,0 +1,125 @@
+use std::env;
+
+use abseil::{MemoryStorage, Persist, Source};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Config {
+    width: Option<u32>,
+    window: Window,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Window {
+    title: String,
+    height: u32,
+    depth: Option<u32>,
+}
+
+fn persist(application: &str) -> Persist {
+    Persist::builder(application)
+        .with_storage(MemoryStorage::new())
+        .build()
+}
+
+fn nowhere() -> std::path::PathBuf {
+    env::temp_dir().join("abseil-layers-test-missing")
+}
+
+#[test]
+fn layers_override_each_other() {
+    let persist = persist("layers-override");
+    persist
+        .store(Config {
+            width: Some(640),
+            window: Window {
+                title: "stored".into(),
+                height: 480,
+                depth: None,
+            },
+        })
+        .unwrap();
+
+    let layered = persist
+        .layers::<Config>()
+        .with_system_dir(nowhere())
+        .with_defaults(Config {
+            width: Some(800),
+            ..Config::default()
+        })
+        .with_override("width", Some(1024u32))
+        .unwrap()
+        .with_override("window.depth", "3")
+        .unwrap()
+        .load()
+        .unwrap();
+
+    assert_eq!(layered.state.width, Some(1024));
+    assert_eq!(layered.state.window.title, "stored");
+    assert_eq!(layered.state.window.depth, Some(3));
+    assert_eq!(layered.source("width"), Some(&Source::Override));
+    assert!(matches!(
+        layered.source("window.title"),
+        Some(Source::User(_))
+    ));
+}
+
+#[test]
+fn environment_variables_are_parsed() {
+    // The prefix is unique to this test, so other tests can't see these variables.
+    env::set_var("LAYERS_ENV_WINDOW__HEIGHT", "720");
+    env::set_var("LAYERS_ENV_WINDOW__DEPTH", "2");
+
+    let layered = persist("layers-env")
+        .layers::<Config>()
+        .with_system_dir(nowhere())
+        .load()
+        .unwrap();
+
+    assert_eq!(layered.state.window.height, 720);
+    assert_eq!(layered.state.window.depth, Some(2));
+    assert_eq!(
+        layered.source("window.height"),
+        Some(&Source::Environment("LAYERS_ENV_WINDOW__HEIGHT".into()))
+    );
+    assert_eq!(layered.source("width"), Some(&Source::Default));
+}
+
+#[test]
+fn other_slots_have_their_own_variables() {
+    env::set_var("LAYERS_SLOT_RECENT_WINDOW__HEIGHT", "5");
+    env::set_var("LAYERS_SLOT_WINDOW__HEIGHT", "9");
+
+    let persist = persist("layers-slot");
+    let recent = persist
+        .slot("recent")
+        .layers::<Config>()
+        .with_system_dir(nowhere())
+        .load()
+        .unwrap();
+    assert_eq!(recent.state.window.height, 5);
+
+    let default = persist
+        .layers::<Config>()
+        .with_system_dir(nowhere())
+        .load()
+        .unwrap();
+    assert_eq!(default.state.window.height, 9);
+}
+
+#[cfg(unix)]
+#[test]
+fn variables_that_are_not_unicode_are_skipped() {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    env::set_var("LAYERS_UNICODE_WIDTH", OsStr::from_bytes(b"\xff"));
+    env::set_var("LAYERS_UNICODE_WINDOW__HEIGHT", "3");
+
+    let layered = persist("layers-unicode")
+        .layers::<Config>()
+        .with_system_dir(nowhere())
+        .load()
+        .unwrap();
+    assert_eq!(layered.state.width, None);
+    assert_eq!(layered.state.window.height, 3);
+}
@@ -0,0 +1,90 @@
+use std::{env, fs, path::Path};
+
+use abseil::{Format, Location, Persist};
+
+/// Where the default slot of a [`Persist`] using the default format lives within `dir`.
+fn document(dir: &Path) -> std::path::PathBuf {
+    dir.join(format!("persist.{}", Format::default().extension()))
+}
+
+#[test]
+fn locations_are_subdirectories_of_the_root() {
+    let root = tempfile::tempdir().unwrap();
+
+    Persist::builder("test")
+        .with_root(root.path())
+        .build()
+        .store(1u32)
+        .unwrap();
+    assert!(document(&root.path().join("config")).exists());
+
+    for (location, dir) in [(Location::Cache, "cache"), (Location::State, "state")] {
+        let persist = Persist::builder("test")
+            .with_root(root.path())
+            .with_location(location)
+            .build();
+        assert_eq!(persist.load::<u32>().unwrap().state, 0);
+        persist.store(2u32).unwrap();
+        assert!(document(&root.path().join(dir)).exists());
+    }
+}
+
+#[test]
+fn the_environment_overrides_the_default_directories() {
+    let root = tempfile::tempdir().unwrap();
+    let persist = Persist::builder("location env").build();
+    assert_eq!(persist.env_var(), "LOCATION_ENV_STATE_DIR");
+
+    env::set_var(persist.env_var(), root.path());
+    persist.store(1u32).unwrap();
+    env::remove_var(persist.env_var());
+
+    assert!(document(&root.path().join("config")).exists());
+}
+
+#[test]
+fn an_explicit_root_beats_the_environment() {
+    let root = tempfile::tempdir().unwrap();
+    let ignored = tempfile::tempdir().unwrap();
+    let persist = Persist::builder("location root")
+        .with_root(root.path())
+        .build();
+
+    env::set_var(persist.env_var(), ignored.path());
+    persist.store(1u32).unwrap();
+    env::remove_var(persist.env_var());
+
+    assert!(document(&root.path().join("config")).exists());
+    assert_eq!(fs::read_dir(ignored.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn the_environment_beats_portable_mode() {
+    let root = tempfile::tempdir().unwrap();
+    let persist = Persist::builder("location portable").portable().build();
+
+    env::set_var(persist.env_var(), root.path());
+    persist.slot("portable-env").store(1u32).unwrap();
+    env::remove_var(persist.env_var());
+
+    let name = format!("portable-env.{}", Format::default().extension());
+    assert!(root.path().join("config").join(name).exists());
+}
+
+#[test]
+fn portable_state_lives_next_to_the_executable() {
+    let persist = Persist::builder("location exe")
+        .with_location(Location::Cache)
+        .portable()
+        .build();
+    persist.slot("portable-exe").store(1u32).unwrap();
+
+    let exe = env::current_exe().unwrap();
+    let dir = exe.parent().unwrap().join("cache");
+    let name = format!("portable-exe.{}", Format::default().extension());
+    let stored = dir.join(name).exists();
+
+    // Nothing else is kept next to the test binary.
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(stored);
+}
@@ -0,0 +1,164 @@
+// The migrations here rely on the default format, which is JSON when it is enabled.
+#![cfg(feature = "json")]
+
+use std::collections::BTreeMap;
+
+use abseil::{Error, MemoryStorage, Persist, Value};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct V1 {
+    name: String,
+}
+
+#[derive(Debug, Default, PartialEq, Deserialize)]
+struct V2 {
+    title: String,
+    count: u32,
+}
+
+/// Rename `name` to `title` and add `count`.
+fn rename(state: Value) -> Result<Value, abseil::BoxError> {
+    let Value::Map(mut map) = state else {
+        return Err("expected a map".into());
+    };
+    let name = map
+        .remove(&Value::String("name".into()))
+        .ok_or("missing name")?;
+    map.insert(Value::String("title".into()), name);
+    map.insert(Value::String("count".into()), Value::U32(1));
+    Ok(Value::Map(map))
+}
+
+fn fail(_: Value) -> Result<Value, abseil::BoxError> {
+    Err("nope".into())
+}
+
+#[test]
+fn old_documents_are_migrated_on_load() {
+    let storage = MemoryStorage::new();
+    let v1 = Persist::builder("test")
+        .with_storage(storage.clone())
+        .build();
+    v1.store(V1 {
+        name: "first".into(),
+    })
+    .unwrap();
+
+    let v2 = Persist::builder("test")
+        .with_storage(storage.clone())
+        .with_version(2)
+        .with_migration(1, rename)
+        .build();
+    let document = v2.load::<V2>().unwrap();
+    assert_eq!(document.version, 2);
+    assert_eq!(
+        document.state,
+        V2 {
+            title: "first".into(),
+            count: 1
+        }
+    );
+
+    // Loading leaves the stored document alone; fields can still be read through migrations.
+    assert_eq!(v1.load::<V1>().unwrap().state.name, "first");
+    assert_eq!(v2.get::<String>("title").unwrap().as_deref(), Some("first"));
+}
+
+#[test]
+fn missing_migrations_are_reported() {
+    let storage = MemoryStorage::new();
+    Persist::builder("test")
+        .with_storage(storage.clone())
+        .build()
+        .store(V1::default())
+        .unwrap();
+
+    let v3 = Persist::builder("test")
+        .with_storage(storage.clone())
+        .with_version(3)
+        .with_migration(1, rename)
+        .build();
+    assert!(matches!(v3.load::<V2>(), Err(Error::Migration(2, _))));
+
+    let failing = Persist::builder("test")
+        .with_storage(storage.clone())
+        .with_version(2)
+        .with_migration(1, fail)
+        .build();
+    assert!(matches!(failing.load::<V2>(), Err(Error::Migration(1, _))));
+}
+
+#[test]
+fn newer_documents_are_refused() {
+    let storage = MemoryStorage::new();
+    Persist::builder("test")
+        .with_storage(storage.clone())
+        .with_version(5)
+        .build()
+        .store(BTreeMap::<String, u32>::new())
+        .unwrap();
+
+    let older = Persist::builder("test")
+        .with_storage(storage.clone())
+        .with_version(4)
+        .build();
+    assert!(matches!(
+        older.load::<BTreeMap<String, u32>>(),
+        Err(Error::Version(5))
+    ));
+}
+
+#[cfg(feature = "ron")]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Theme {
+    Light,
+    Custom(String),
+}
+
+#[cfg(feature = "ron")]
+#[test]
+fn ron_enums_round_trip() {
+    let persist = Persist::builder("test")
+        .with_storage(MemoryStorage::new())
+        .with_format(abseil::Format::Ron)
+        .build();
+    let themes = vec![Theme::Light, Theme::Custom("dusk".into())];
+    persist.store(&themes).unwrap();
+    assert_eq!(persist.load::<Vec<Theme>>().unwrap().state, themes);
+}
+
+#[cfg(feature = "ron")]
+#[test]
+#[should_panic = "migrations can't be used with the RON format"]
+fn ron_migrations_are_refused() {
+    Persist::builder("test")
+        .with_storage(MemoryStorage::new())
+        .with_format(abseil::Format::Ron)
+        .with_version(2)
+        .with_migration(1, Ok)
+        .build();
+}
+
+#[cfg(feature = "ron")]
+#[test]
+fn out_of_date_ron_documents_are_refused() {
+    let storage = MemoryStorage::new();
+    Persist::builder("test")
+        .with_storage(storage.clone())
+        .with_format(abseil::Format::Ron)
+        .build()
+        .store(vec![Theme::Light])
+        .unwrap();
+
+    let json = Persist::builder("test")
+        .with_storage(storage.clone())
+        .with_format(abseil::Format::Json)
+        .with_version(2)
+        .with_migration(1, Ok)
+        .build();
+    assert!(matches!(
+        json.load::<Vec<Theme>>(),
+        Err(Error::Migration(1, _))
+    ));
+}
@@ -0,0 +1,111 @@
+use abseil::{Error, MemoryStorage, Outcome, Persist, Recovery};
+
+/// A `Persist` whose default slot holds something that can't be parsed.
+fn corrupt(recovery: Recovery) -> (MemoryStorage, Persist) {
+    let storage = MemoryStorage::new();
+    let persist = Persist::builder("test")
+        .with_storage(storage.clone())
+        .with_recovery(recovery)
+        .build();
+    persist.store(1u32).unwrap();
+
+    let key = storage.keys().pop().unwrap();
+    storage.insert(key, b"\x00\xff not a document".to_vec());
+    (storage, persist)
+}
+
+#[test]
+fn fail_returns_the_error() {
+    let (storage, persist) = corrupt(Recovery::Fail);
+    let keys = storage.keys();
+
+    assert!(matches!(
+        persist.load::<u32>(),
+        Err(Error::Serialization(_))
+    ));
+    assert_eq!(storage.keys(), keys);
+}
+
+#[test]
+fn default_resets_state_and_leaves_the_file() {
+    let (storage, persist) = corrupt(Recovery::Default);
+    let keys = storage.keys();
+
+    let (document, outcome) = persist.load_with_outcome::<u32>().unwrap();
+    assert_eq!(document.state, 0);
+    assert!(matches!(outcome, Outcome::Reset(_)));
+    assert_eq!(storage.keys(), keys);
+
+    // The reset state may replace the bad file.
+    let mut document = document;
+    document.state = 2;
+    persist.store_if_unchanged(&mut document).unwrap();
+    assert_eq!(persist.load::<u32>().unwrap().state, 2);
+}
+
+#[test]
+fn quarantine_moves_the_file_aside() {
+    let (storage, persist) = corrupt(Recovery::Quarantine);
+    let key = storage.keys().pop().unwrap();
+
+    let (document, outcome) = persist.load_with_outcome::<u32>().unwrap();
+    assert_eq!(document.state, 0);
+    let Outcome::Quarantined(quarantined, _) = outcome else {
+        panic!("expected quarantine, got {outcome:?}");
+    };
+
+    assert!(quarantined.starts_with(&format!("{key}.corrupt-")));
+    assert_eq!(storage.keys(), [quarantined]);
+    assert!(matches!(
+        persist.load_with_outcome::<u32>().unwrap().1,
+        Outcome::Missing
+    ));
+}
+
+#[test]
+fn repeated_quarantines_keep_every_file() {
+    let (storage, persist) = corrupt(Recovery::Quarantine);
+    let key = storage.keys().pop().unwrap();
+    persist.load::<u32>().unwrap();
+
+    storage.insert(key.clone(), b"\x00 also not a document".to_vec());
+    persist.load::<u32>().unwrap();
+
+    let keys = storage.keys();
+    assert_eq!(keys.len(), 2);
+    assert!(keys
+        .iter()
+        .all(|k| k.starts_with(&format!("{key}.corrupt-"))));
+}
+
+#[test]
+fn borrowed_loads_quarantine_too() {
+    let (storage, persist) = corrupt(Recovery::Quarantine);
+
+    let loaded = persist.load_borrowed().unwrap();
+    assert_eq!(loaded.get::<u32>().unwrap().state, 0);
+    assert!(matches!(loaded.outcome(), Outcome::Quarantined(..)));
+    assert_eq!(storage.keys().len(), 1);
+    assert!(storage.keys()[0].contains(".corrupt-"));
+}
+
+#[test]
+fn update_quarantines_and_starts_over() {
+    let (storage, persist) = corrupt(Recovery::Quarantine);
+
+    persist.update(|state: &mut u32| *state += 5).unwrap();
+    assert_eq!(persist.load::<u32>().unwrap().state, 5);
+    assert_eq!(storage.keys().len(), 2);
+}
+
+#[test]
+fn borrowed_loads_report_their_outcome() {
+    let (_, persist) = corrupt(Recovery::Default);
+    let loaded = persist.load_borrowed().unwrap();
+    assert!(matches!(loaded.outcome(), Outcome::Reset(_)));
+
+    persist.store(3u32).unwrap();
+    let loaded = persist.load_borrowed().unwrap();
+    assert!(matches!(loaded.outcome(), Outcome::Loaded));
+    assert_eq!(loaded.get::<u32>().unwrap().state, 3);
+}
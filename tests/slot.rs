@@ -0,0 +1,215 @@
+use std::{fs, path::Path};
+
+use abseil::{Error, Format, MemoryStorage, Persist};
+
+#[cfg(feature = "toml")]
+#[test]
+fn legacy_json_file_is_loaded_and_replaced() {
+    let storage = MemoryStorage::new();
+    storage.insert(
+        "persist.json",
+        "timestamp = \"2024-01-01T00:00:00Z\"\nversion = 1\nstate = [1, 2, 3]\n",
+    );
+
+    let persist = Persist::builder("test")
+        .with_storage(storage.clone())
+        .with_format(Format::Toml)
+        .build();
+
+    assert_eq!(persist.slots().unwrap(), ["persist"]);
+    assert_eq!(persist.load::<Vec<u32>>().unwrap().state, [1, 2, 3]);
+
+    persist.store(vec![4u32]).unwrap();
+    assert_eq!(storage.keys(), ["persist.toml"]);
+    assert_eq!(persist.load::<Vec<u32>>().unwrap().state, [4]);
+}
+
+#[test]
+fn state_is_stored_under_the_format_extension() {
+    let storage = MemoryStorage::new();
+    let format = Format::ALL[0];
+    let persist = Persist::builder("test")
+        .with_storage(storage.clone())
+        .with_format(format)
+        .build();
+
+    persist.store(vec![1u32]).unwrap();
+    assert_eq!(storage.keys(), [format!("persist.{}", format.extension())]);
+    assert_eq!(persist.slots().unwrap(), ["persist"]);
+}
+
+/// The names of the files in the single directory `persist` created under `root`.
+fn files(root: &Path) -> Vec<String> {
+    let dir = fs::read_dir(root).unwrap().next().unwrap().unwrap().path();
+    let mut names: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| !name.starts_with('.'))
+        .collect();
+    names.sort();
+    names
+}
+
+#[cfg(all(feature = "json", feature = "yaml"))]
+#[test]
+fn contents_are_sniffed_whatever_the_extension() {
+    let storage = MemoryStorage::new();
+    storage.insert(
+        "persist.json",
+        "timestamp: 2024-01-01T00:00:00Z\nversion: 1\nstate: [1, 2]\n",
+    );
+
+    let persist = Persist::builder("test")
+        .with_storage(storage.clone())
+        .with_format(Format::Json)
+        .build();
+    assert_eq!(persist.load::<Vec<u32>>().unwrap().state, [1, 2]);
+
+    // The document stays in the format it was found in.
+    persist.store(vec![3u32]).unwrap();
+    assert_eq!(storage.keys(), ["persist.yaml"]);
+}
+
+#[cfg(all(feature = "json", feature = "toml"))]
+#[test]
+fn documents_are_converted_when_asked() {
+    let storage = MemoryStorage::new();
+    let json = Persist::builder("test")
+        .with_storage(storage.clone())
+        .with_format(Format::Json)
+        .build();
+    json.store(vec![1u32]).unwrap();
+
+    let toml = Persist::builder("test")
+        .with_storage(storage.clone())
+        .with_format(Format::Toml)
+        .build();
+    assert_eq!(toml.load::<Vec<u32>>().unwrap().state, [1]);
+    toml.store(vec![2u32]).unwrap();
+    assert_eq!(storage.keys(), ["persist.json"]);
+
+    let toml = Persist::builder("test")
+        .with_storage(storage.clone())
+        .with_format(Format::Toml)
+        .convert_on_store()
+        .build();
+    toml.store(vec![3u32]).unwrap();
+    assert_eq!(storage.keys(), ["persist.toml"]);
+    assert_eq!(json.load::<Vec<u32>>().unwrap().state, [3]);
+}
+
+#[test]
+fn store_if_unchanged_detects_conflicts() {
+    let root = tempfile::tempdir().unwrap();
+    let persist = Persist::builder("test").with_root(root.path()).build();
+    persist.store(1u32).unwrap();
+
+    let mut ours = persist.load::<u32>().unwrap();
+    let mut theirs = persist.load::<u32>().unwrap();
+
+    ours.state = 2;
+    persist.store_if_unchanged(&mut ours).unwrap();
+
+    theirs.state = 3;
+    assert!(matches!(
+        persist.store_if_unchanged(&mut theirs),
+        Err(Error::Conflict)
+    ));
+    assert_eq!(persist.load::<u32>().unwrap().state, 2);
+
+    // A successful store can be followed by another.
+    ours.state = 4;
+    persist.store_if_unchanged(&mut ours).unwrap();
+    assert_eq!(persist.load::<u32>().unwrap().state, 4);
+}
+
+#[test]
+fn update_modifies_state_in_place() {
+    let persist = Persist::builder("test")
+        .with_storage(MemoryStorage::new())
+        .build();
+
+    for _ in 0..3 {
+        persist
+            .update(|state: &mut Vec<u32>| state.push(1))
+            .unwrap();
+    }
+    assert_eq!(persist.load::<Vec<u32>>().unwrap().state, [1, 1, 1]);
+}
+
+#[test]
+fn concurrent_updates_on_disk_are_not_lost() {
+    let root = tempfile::tempdir().unwrap();
+
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                // Separate instances, as separate processes would have.
+                let persist = Persist::builder("test").with_root(root.path()).build();
+                for _ in 0..10 {
+                    persist.update(|count: &mut u32| *count += 1).unwrap();
+                }
+            });
+        }
+    });
+
+    let persist = Persist::builder("test").with_root(root.path()).build();
+    assert_eq!(persist.load::<u32>().unwrap().state, 40);
+}
+
+#[test]
+fn names_differing_in_case_are_kept_apart() {
+    let root = tempfile::tempdir().unwrap();
+    let persist = Persist::builder("test").with_root(root.path()).build();
+
+    persist.slot("Recent").store(1u32).unwrap();
+    persist.slot("recent").store(2u32).unwrap();
+
+    // Distinct even on a filesystem which ignores case.
+    let mut names: Vec<_> = files(root.path())
+        .iter()
+        .map(|name| name.to_lowercase())
+        .collect();
+    names.dedup();
+    assert_eq!(names.len(), 2);
+
+    assert_eq!(persist.slots().unwrap(), ["Recent", "recent"]);
+    assert_eq!(persist.slot("Recent").load::<u32>().unwrap().state, 1);
+    assert_eq!(persist.slot("recent").load::<u32>().unwrap().state, 2);
+}
+
+#[test]
+fn slot_names_are_escaped() {
+    let root = tempfile::tempdir().unwrap();
+    let persist = Persist::builder("test").with_root(root.path()).build();
+
+    for name in ["a/b", "..", "con", "with space", "ünï"] {
+        persist.slot(name).store(name).unwrap();
+    }
+
+    assert!(files(root.path()).iter().all(|name| name
+        .bytes()
+        .all(|byte| byte.is_ascii_graphic() && byte != b'/')));
+    assert_eq!(
+        persist.slots().unwrap(),
+        ["..", "a/b", "con", "with space", "ünï"]
+    );
+    assert_eq!(persist.slot("a/b").load::<String>().unwrap().state, "a/b");
+}
+
+#[test]
+fn slots_are_listed_and_deleted() {
+    let root = tempfile::tempdir().unwrap();
+    let persist = Persist::builder("test").with_root(root.path()).build();
+    persist.store(1u32).unwrap();
+    persist.slot("other").store(2u32).unwrap();
+
+    assert_eq!(persist.slots().unwrap(), ["other", "persist"]);
+    assert!(persist.slot("other").delete().unwrap());
+    assert!(!persist.slot("other").delete().unwrap());
+    assert_eq!(persist.slots().unwrap(), ["persist"]);
+    assert!(matches!(
+        persist.slot("").load::<u32>(),
+        Err(Error::InvalidSlot(_))
+    ));
+}
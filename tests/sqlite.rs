@@ -0,0 +1,93 @@
+#![cfg(feature = "sqlite")]
+
+use abseil::{Backups, Persist, SqliteStorage};
+use serde::{ser::Error as _, Serialize, Serializer};
+
+fn in_memory() -> Persist {
+    Persist::builder("test")
+        .with_storage(SqliteStorage::open_in_memory().unwrap())
+        .build()
+}
+
+/// State which can never be serialized.
+struct Unserializable;
+
+impl Serialize for Unserializable {
+    fn serialize<S: Serializer>(&self, _: S) -> Result<S::Ok, S::Error> {
+        Err(S::Error::custom("unserializable"))
+    }
+}
+
+#[test]
+fn slots_are_stored_as_rows() {
+    let persist = in_memory();
+    persist.store(1u32).unwrap();
+    persist.slot("a/b").store(2u32).unwrap();
+
+    assert_eq!(persist.load::<u32>().unwrap().state, 1);
+    assert_eq!(persist.slot("a/b").load::<u32>().unwrap().state, 2);
+    assert_eq!(persist.slots().unwrap(), ["a/b", "persist"]);
+
+    assert!(persist.slot("a/b").delete().unwrap());
+    assert_eq!(persist.slots().unwrap(), ["persist"]);
+}
+
+#[test]
+fn with_sqlite_keeps_a_database_in_the_data_directory() {
+    let root = tempfile::tempdir().unwrap();
+    let persist = Persist::builder("test")
+        .with_root(root.path())
+        .with_sqlite()
+        .build();
+    persist.store(vec![1u32, 2]).unwrap();
+
+    assert!(root.path().join("data").join("abseil.sqlite3").exists());
+
+    let reopened = Persist::builder("test")
+        .with_root(root.path())
+        .with_sqlite()
+        .build();
+    assert_eq!(reopened.load::<Vec<u32>>().unwrap().state, [1, 2]);
+}
+
+#[test]
+fn batches_write_every_slot() {
+    let persist = in_memory();
+    persist.slot("a").store(0u32).unwrap();
+
+    let mut batch = persist.batch();
+    batch.store("a", 1u32).store("b", 2u32).store("a", 3u32);
+    batch.commit().unwrap();
+
+    assert_eq!(persist.slot("a").load::<u32>().unwrap().state, 3);
+    assert_eq!(persist.slot("b").load::<u32>().unwrap().state, 2);
+}
+
+#[test]
+fn failed_batches_write_nothing() {
+    let persist = in_memory();
+    persist.slot("a").store(0u32).unwrap();
+
+    let mut batch = persist.batch();
+    batch.store("a", 1u32).store("b", Unserializable);
+    assert!(batch.commit().is_err());
+
+    assert_eq!(persist.slot("a").load::<u32>().unwrap().state, 0);
+    assert_eq!(persist.slots().unwrap(), ["a"]);
+}
+
+#[test]
+fn batches_take_backups() {
+    let persist = Persist::builder("test")
+        .with_storage(SqliteStorage::open_in_memory().unwrap())
+        .with_backups(Backups::Keep(2))
+        .build();
+    persist.store(1u32).unwrap();
+
+    let mut batch = persist.batch();
+    batch.store("persist", 2u32);
+    batch.commit().unwrap();
+
+    assert_eq!(persist.backups().unwrap().len(), 1);
+    assert_eq!(persist.load::<u32>().unwrap().state, 2);
+}
@@ -0,0 +1,73 @@
+#![cfg(feature = "watch")]
+
+use std::{fs, path::Path, time::Duration};
+
+use abseil::{Format, Persist};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The directory holding the default location's files under `root`.
+fn dir(root: &Path) -> std::path::PathBuf {
+    root.join("config")
+}
+
+#[test]
+fn own_writes_are_not_reported() {
+    let root = tempfile::tempdir().unwrap();
+    let persist = Persist::builder("test").with_root(root.path()).build();
+    persist.store(1u32).unwrap();
+
+    let watch = persist.watch::<u32>().unwrap();
+    persist.store(2u32).unwrap();
+    persist.slot("other").store(3u32).unwrap();
+
+    assert!(watch.recv_timeout(Duration::from_millis(500)).is_none());
+}
+
+#[test]
+fn external_edits_are_delivered() {
+    let root = tempfile::tempdir().unwrap();
+    let persist = Persist::builder("test").with_root(root.path()).build();
+    persist.store(1u32).unwrap();
+    let watch = persist.watch::<u32>().unwrap();
+
+    // A document written elsewhere and copied over is as good as one from another process.
+    let elsewhere = tempfile::tempdir().unwrap();
+    Persist::builder("test")
+        .with_root(elsewhere.path())
+        .build()
+        .store(5u32)
+        .unwrap();
+    let name = format!("persist.{}", Format::default().extension());
+    fs::copy(
+        dir(elsewhere.path()).join(&name),
+        dir(root.path()).join(&name),
+    )
+    .unwrap();
+
+    let document = watch.recv_timeout(TIMEOUT).unwrap().unwrap();
+    assert_eq!(document.state, 5);
+
+    fs::write(dir(root.path()).join(&name), [0xff, 0x00, 0xff]).unwrap();
+    assert!(watch.recv_timeout(TIMEOUT).unwrap().is_err());
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn legacy_json_files_are_watched() {
+    let root = tempfile::tempdir().unwrap();
+    let persist = Persist::builder("test")
+        .with_root(root.path())
+        .with_format(Format::Toml)
+        .build();
+
+    let watch = persist.watch::<u32>().unwrap();
+    fs::write(
+        dir(root.path()).join("persist.json"),
+        "timestamp = \"2024-01-01T00:00:00Z\"\nversion = 1\nstate = 5\n",
+    )
+    .unwrap();
+
+    let document = watch.recv_timeout(TIMEOUT).unwrap().unwrap();
+    assert_eq!(document.state, 5);
+}